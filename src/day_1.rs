@@ -1,55 +1,212 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashMap;
+use itertools::Itertools;
+use pathfinding::kuhn_munkres::kuhn_munkres_min;
+use pathfinding::matrix::Matrix;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
 use std::iter::zip;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 struct Lists {
-    left: Vec<i32>,
-    right: Vec<i32>,
+    left: Vec<i64>,
+    right: Vec<i64>,
 }
 
-fn parse_line(line: &str) -> (i32, i32) {
-    if let [Ok(a), Ok(b)] = line
-        .split_whitespace()
-        .map(|s| s.parse::<i32>())
-        .collect::<Vec<_>>()[..]
-    {
-        return (a, b);
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    InvalidNumber {
+        line: usize,
+        token: String,
+    },
+    ColumnCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    Io(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber { line, token } => {
+                write!(f, "line {}: {:?} is not a number", line, token)
+            }
+            ParseError::ColumnCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} columns, found {}. Columns have unequal lengths",
+                line, expected, found
+            ),
+            ParseError::Io(e) => write!(f, "failed to read input: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_line(line: &str, line_number: usize) -> Result<Vec<i64>, ParseError> {
+    line.split_whitespace()
+        .map(|s| {
+            s.parse::<i64>().map_err(|_| ParseError::InvalidNumber {
+                line: line_number,
+                token: s.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whitespace separated columns of numbers. The first non-empty line decides the column count.
+fn parse_columns<R: BufRead>(reader: R) -> Result<Vec<Vec<i64>>, ParseError> {
+    let mut columns: Vec<Vec<i64>> = vec![];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| ParseError::Io(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let values = parse_line(&line, i + 1)?;
+        if columns.is_empty() {
+            columns = vec![vec![]; values.len()];
+        }
+        if values.len() != columns.len() {
+            return Err(ParseError::ColumnCount {
+                line: i + 1,
+                expected: columns.len(),
+                found: values.len(),
+            });
+        }
+
+        for (column, value) in columns.iter_mut().zip(values) {
+            column.push(value);
+        }
     }
 
-    panic!("Failed to parse line: {}", line);
+    Ok(columns)
 }
 
 #[aoc_generator(day1)]
-fn input_generator(input: &str) -> Lists {
-    let mut left = vec![];
-    let mut right = vec![];
-    for line in input.lines() {
-        let (a, b) = parse_line(line);
-        left.push(a);
-        right.push(b);
+fn input_generator(input: &str) -> Result<Lists, ParseError> {
+    let columns = parse_columns(input.as_bytes())?;
+
+    match <[Vec<i64>; 2]>::try_from(columns) {
+        Ok([left, right]) => Ok(Lists { left, right }),
+        Err(columns) => Err(ParseError::ColumnCount {
+            line: 1,
+            expected: 2,
+            found: columns.len(),
+        }),
     }
+}
 
-    Lists { left, right }
+#[derive(Debug, Clone, Copy)]
+enum Cost {
+    Absolute,
+    Squared,
+    Capped(i64),
 }
 
-#[aoc(day1, part1)]
-fn part1(input_lists: &Lists) -> i32 {
-    let mut left = input_lists.left.clone();
-    left.sort();
-    let mut right = input_lists.right.clone();
-    right.sort();
+impl Cost {
+    fn cost(&self, l: i64, r: i64) -> i64 {
+        let diff = (l - r).abs();
+        match self {
+            Cost::Absolute => diff,
+            Cost::Squared => diff.saturating_mul(diff),
+            Cost::Capped(cap) => diff.min(*cap),
+        }
+    }
 
-    let mut total = 0;
+    // Pairing sorted lists is optimal when the cost is a convex function of the distance
+    fn sorting_is_optimal(&self) -> bool {
+        match self {
+            Cost::Absolute | Cost::Squared => true,
+            Cost::Capped(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Pairing {
+    total: i64,
+    pairs: Vec<(i64, i64)>,
+}
+
+/// Min-cost perfect matching between equally long lists. Non-convex costs fall back to the
+/// O(n³) Hungarian algorithm.
+fn min_cost_pairing(left: &[i64], right: &[i64], cost: Cost) -> Pairing {
+    assert_eq!(left.len(), right.len(), "lists must be equally long");
 
-    for (l, r) in zip(left, right) {
-        total += (l - r).abs();
+    let pairs = if cost.sorting_is_optimal() || left.is_empty() {
+        zip(
+            left.iter().copied().sorted(),
+            right.iter().copied().sorted(),
+        )
+        .collect_vec()
+    } else {
+        let weights = Matrix::from_fn(left.len(), right.len(), |(l, r)| {
+            cost.cost(left[l], right[r])
+        });
+        let (_, assignments) = kuhn_munkres_min(&weights);
+        assignments
+            .into_iter()
+            .enumerate()
+            .map(|(l, r)| (left[l], right[r]))
+            .collect_vec()
+    };
+
+    Pairing {
+        total: pairs.iter().map(|&(l, r)| cost.cost(l, r)).sum(),
+        pairs,
     }
+}
+
+// Distances beyond this count as a plain mismatch in the diagnostics
+const DIAGNOSTICS_CAP: i64 = 1000;
 
-    total
+pub(crate) fn diagnostics(input: &str) -> String {
+    let lists = match input_generator(input) {
+        Ok(lists) => lists,
+        Err(e) => return e.to_string(),
+    };
+    let sorted = min_cost_pairing(&lists.left, &lists.right, Cost::Absolute);
+    let squared = min_cost_pairing(&lists.left, &lists.right, Cost::Squared);
+    let capped = min_cost_pairing(&lists.left, &lists.right, Cost::Capped(DIAGNOSTICS_CAP));
+    let differing = sorted
+        .pairs
+        .iter()
+        .filter(|pair| !capped.pairs.contains(pair))
+        .count();
+
+    [
+        format!(
+            "{} pairs, total distance {}",
+            sorted.pairs.len(),
+            sorted.total
+        ),
+        format!("Squared distance {}", squared.total),
+        format!(
+            "Distance capped at {}: {}, {} pairs differ from the sorted pairing",
+            DIAGNOSTICS_CAP, capped.total, differing
+        ),
+    ]
+    .join("\n")
 }
 
-fn count_entries(list: &Vec<i32>) -> HashMap<i32, i32> {
-    let mut counts: HashMap<i32, i32> = HashMap::new();
+#[aoc(day1, part1)]
+fn part1(input_lists: &Lists) -> i64 {
+    min_cost_pairing(&input_lists.left, &input_lists.right, Cost::Absolute).total
+}
+
+fn count_entries(list: &[i64]) -> HashMap<i64, i64> {
+    let mut counts: HashMap<i64, i64> = HashMap::new();
 
     for item in list {
         *counts.entry(*item).or_insert(0) += 1;
@@ -59,7 +216,7 @@ fn count_entries(list: &Vec<i32>) -> HashMap<i32, i32> {
 }
 
 #[aoc(day1, part2)]
-fn part2(input_lists: &Lists) -> i32 {
+fn part2(input_lists: &Lists) -> i64 {
     let counts = count_entries(&input_lists.right);
 
     input_lists
@@ -69,6 +226,260 @@ fn part2(input_lists: &Lists) -> i32 {
     // 0
 }
 
+const DEFAULT_MEMORY_BUDGET: usize = 64 << 20;
+const DEFAULT_MAX_FAN_IN: usize = 64;
+
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+struct ExternalConfig {
+    memory_budget: usize, // bytes of list values held in memory while sorting
+    temp_dir: PathBuf,
+    max_fan_in: usize, // runs of one list open at the same time while merging
+}
+
+impl Default for ExternalConfig {
+    fn default() -> Self {
+        ExternalConfig {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            temp_dir: std::env::temp_dir(),
+            max_fan_in: DEFAULT_MAX_FAN_IN,
+        }
+    }
+}
+
+/// A sorted run of values spilled to disk. Removed when dropped.
+struct Run {
+    path: PathBuf,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn run_path(config: &ExternalConfig) -> PathBuf {
+    config.temp_dir.join(format!(
+        "aoc2024-day1-{}-{}.run",
+        std::process::id(),
+        NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+fn spill(values: &mut Vec<i64>, config: &ExternalConfig) -> io::Result<Run> {
+    values.sort_unstable();
+
+    let path = run_path(config);
+    let mut writer = BufWriter::new(File::create(&path)?);
+    for value in values.drain(..) {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()?;
+
+    Ok(Run { path })
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+}
+
+impl RunReader {
+    fn read_value(&mut self) -> io::Result<Option<i64>> {
+        let mut bytes = [0; 8];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(i64::from_le_bytes(bytes))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// K-way merge of sorted runs.
+struct Merged {
+    readers: Vec<RunReader>,
+    heap: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl Merged {
+    fn new(runs: &[Run], buffer_size: usize) -> io::Result<Merged> {
+        let mut merged = Merged {
+            readers: vec![],
+            heap: BinaryHeap::new(),
+        };
+        for (i, run) in runs.iter().enumerate() {
+            let mut reader = RunReader {
+                reader: BufReader::with_capacity(buffer_size, File::open(&run.path)?),
+            };
+            if let Some(value) = reader.read_value()? {
+                merged.heap.push(Reverse((value, i)));
+            }
+            merged.readers.push(reader);
+        }
+        Ok(merged)
+    }
+
+    fn peek(&self) -> Option<i64> {
+        self.heap.peek().map(|Reverse((value, _))| *value)
+    }
+
+    fn pop(&mut self) -> io::Result<Option<i64>> {
+        let Some(Reverse((value, i))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(next) = self.readers[i].read_value()? {
+            self.heap.push(Reverse((next, i)));
+        }
+        Ok(Some(value))
+    }
+}
+
+/// Merges groups of runs into longer ones until at most `max_fan_in` are left, so that no pass
+/// holds more than `max_fan_in` files open.
+fn reduce_runs(runs: Vec<Run>, config: &ExternalConfig) -> io::Result<Vec<Run>> {
+    let fan_in = config.max_fan_in.max(2);
+    let buffer_size = (config.memory_budget / (fan_in + 1)).max(64);
+
+    let mut runs = runs;
+    while runs.len() > fan_in {
+        let mut next = vec![];
+        let mut rest = runs.into_iter().peekable();
+        while rest.peek().is_some() {
+            let group = rest.by_ref().take(fan_in).collect_vec();
+            if group.len() == 1 {
+                next.extend(group);
+                continue;
+            }
+
+            let path = run_path(config);
+            // Registered before writing so that a failed pass cleans up after itself
+            next.push(Run { path: path.clone() });
+            let mut merged = Merged::new(&group, buffer_size)?;
+            let mut writer = BufWriter::with_capacity(buffer_size, File::create(&path)?);
+            while let Some(value) = merged.pop()? {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.flush()?;
+        }
+        runs = next;
+    }
+    Ok(runs)
+}
+
+/// Both lists as sorted runs on disk, for lists that do not fit into memory.
+struct ExternalLists {
+    left: Vec<Run>,
+    right: Vec<Run>,
+    buffer_size: usize,
+}
+
+fn external_lists<R: Read>(
+    reader: R,
+    config: &ExternalConfig,
+) -> Result<ExternalLists, ParseError> {
+    let io_error = |e: io::Error| ParseError::Io(e.to_string());
+    let per_list = (config.memory_budget / 2 / size_of::<i64>()).max(1);
+
+    let mut left_runs = vec![];
+    let mut right_runs = vec![];
+    let mut left = Vec::with_capacity(per_list);
+    let mut right = Vec::with_capacity(per_list);
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line.map_err(io_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let values = parse_line(&line, i + 1)?;
+        let [l, r] = values[..] else {
+            return Err(ParseError::ColumnCount {
+                line: i + 1,
+                expected: 2,
+                found: values.len(),
+            });
+        };
+        left.push(l);
+        right.push(r);
+
+        if left.len() == per_list {
+            left_runs.push(spill(&mut left, config).map_err(io_error)?);
+            right_runs.push(spill(&mut right, config).map_err(io_error)?);
+        }
+    }
+    if !left.is_empty() {
+        left_runs.push(spill(&mut left, config).map_err(io_error)?);
+        right_runs.push(spill(&mut right, config).map_err(io_error)?);
+    }
+
+    let left_runs = reduce_runs(left_runs, config).map_err(io_error)?;
+    let right_runs = reduce_runs(right_runs, config).map_err(io_error)?;
+
+    // The merge buffers share the same budget
+    let buffer_size = (config.memory_budget / (2 * left_runs.len()).max(1)).max(64);
+
+    Ok(ExternalLists {
+        left: left_runs,
+        right: right_runs,
+        buffer_size,
+    })
+}
+
+impl ExternalLists {
+    fn distance(&self) -> io::Result<i64> {
+        let mut left = Merged::new(&self.left, self.buffer_size)?;
+        let mut right = Merged::new(&self.right, self.buffer_size)?;
+
+        let mut total = 0;
+        while let (Some(l), Some(r)) = (left.pop()?, right.pop()?) {
+            total += (l - r).abs();
+        }
+        Ok(total)
+    }
+
+    // Merge join of the sorted lists, same as the `count_entries` lookup
+    fn similarity(&self) -> io::Result<i64> {
+        let mut left = Merged::new(&self.left, self.buffer_size)?;
+        let mut right = Merged::new(&self.right, self.buffer_size)?;
+
+        let mut total = 0;
+        let mut last_count: Option<(i64, i64)> = None;
+        while let Some(l) = left.pop()? {
+            let count = match last_count {
+                Some((value, count)) if value == l => count,
+                _ => {
+                    let mut count = 0;
+                    while let Some(r) = right.peek().filter(|r| *r <= l) {
+                        if r == l {
+                            count += 1;
+                        }
+                        right.pop()?;
+                    }
+                    count
+                }
+            };
+            last_count = Some((l, count));
+            total += l * count;
+        }
+        Ok(total)
+    }
+}
+
+#[aoc_generator(day1, part1, external)]
+#[aoc_generator(day1, part2, external)]
+fn external_generator(input: &str) -> Result<ExternalLists, ParseError> {
+    external_lists(input.as_bytes(), &ExternalConfig::default())
+}
+
+#[aoc(day1, part1, external)]
+fn part1_external(lists: &ExternalLists) -> io::Result<i64> {
+    lists.distance()
+}
+
+#[aoc(day1, part2, external)]
+fn part2_external(lists: &ExternalLists) -> io::Result<i64> {
+    lists.similarity()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,13 +494,13 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        let input = input_generator(TEST_INPUT);
+        let input = input_generator(TEST_INPUT).unwrap();
         assert_eq!(part1(&input), 11);
     }
 
     #[test]
     fn test_part2() {
-        let input = input_generator(TEST_INPUT);
+        let input = input_generator(TEST_INPUT).unwrap();
         assert_eq!(part2(&input), 31);
     }
 
@@ -102,4 +513,118 @@ mod tests {
             HashMap::from([(1, 1), (2, 1), (3, 3), (4, 2), (5, 1)])
         )
     }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("1 2 3\n-4 5 6000000000\n".as_bytes()),
+            Ok(vec![vec![1, -4], vec![2, 5], vec![3, 6000000000]])
+        );
+        assert_eq!(
+            parse_columns("1 2\n3\n".as_bytes()),
+            Err(ParseError::ColumnCount {
+                line: 2,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            parse_columns("1 x\n".as_bytes()),
+            Err(ParseError::InvalidNumber {
+                line: 1,
+                token: "x".to_string()
+            })
+        );
+        assert!(input_generator("1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = diagnostics(TEST_INPUT);
+        let lines = report.lines().collect_vec();
+        assert_eq!(lines[0], "6 pairs, total distance 11");
+        assert_eq!(lines[1], "Squared distance 35");
+        assert!(lines[2].starts_with("Distance capped at 1000: 11,"));
+        assert_eq!(
+            diagnostics("1 2 3"),
+            ParseError::ColumnCount {
+                line: 1,
+                expected: 2,
+                found: 3
+            }
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_min_cost_pairing() {
+        let input = input_generator(TEST_INPUT).unwrap();
+        assert_eq!(
+            min_cost_pairing(&input.left, &input.right, Cost::Squared).total,
+            35
+        );
+
+        // Sorting would give 1 + 1 + 5
+        let pairing = min_cost_pairing(&[0, 1, 2], &[1, 2, 100], Cost::Capped(5));
+        assert_eq!(pairing.total, 5);
+        let pairing = min_cost_pairing(&[0, 10, 20], &[11, 21, 100], Cost::Capped(5));
+        assert_eq!(pairing.total, 1 + 1 + 5);
+        assert_eq!(pairing.pairs, vec![(0, 100), (10, 11), (20, 21)]);
+    }
+
+    fn small_budget(name: &str) -> ExternalConfig {
+        let temp_dir =
+            std::env::temp_dir().join(format!("aoc2024-day1-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        ExternalConfig {
+            memory_budget: 32, // two values per list
+            temp_dir,
+            max_fan_in: 4,
+        }
+    }
+
+    #[test]
+    fn test_external() {
+        let config = small_budget("example");
+        let lists = external_lists(TEST_INPUT.as_bytes(), &config).unwrap();
+        assert_eq!(lists.left.len(), 3);
+        assert_eq!(part1_external(&lists).unwrap(), 11);
+        assert_eq!(part2_external(&lists).unwrap(), 31);
+
+        // Runs are removed with the lists
+        drop(lists);
+        assert_eq!(fs::read_dir(&config.temp_dir).unwrap().count(), 0);
+        fs::remove_dir(&config.temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_external_matches_in_memory() {
+        let input = (0..1000i64)
+            .map(|i| format!("{}   {}", (i * 7919) % 101, (i * 104729) % 97 - 40))
+            .join("\n");
+        let config = small_budget("generated");
+        let lists = external_lists(input.as_bytes(), &config).unwrap();
+        let in_memory = input_generator(&input).unwrap();
+        // 500 spilled runs per list, merged in passes of four
+        assert_eq!((lists.left.len(), lists.right.len()), (2, 2));
+        assert_eq!(fs::read_dir(&config.temp_dir).unwrap().count(), 4);
+
+        assert_eq!(lists.distance().unwrap(), part1(&in_memory));
+        assert_eq!(lists.similarity().unwrap(), part2(&in_memory));
+
+        drop(lists);
+        fs::remove_dir(&config.temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_external_errors() {
+        let config = small_budget("errors");
+        assert!(matches!(
+            external_lists("1 2\n3 4 5\n".as_bytes(), &config),
+            Err(ParseError::ColumnCount { line: 2, .. })
+        ));
+        // Runs spilled before the error are cleaned up
+        assert_eq!(fs::read_dir(&config.temp_dir).unwrap().count(), 0);
+        fs::remove_dir(&config.temp_dir).unwrap();
+    }
 }
@@ -1,3 +1,4 @@
+use crate::report::Render;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
@@ -158,6 +159,26 @@ fn part2(input: &Input) -> usize {
         .sum()
 }
 
+pub(crate) fn render(input: &str) -> Option<Render> {
+    let input = parse_input(input);
+
+    let width = input.nodes.iter().map(|n| n.1 + 1).max().unwrap_or(0) as usize;
+    let height = input.nodes.iter().map(|n| n.0 + 1).max().unwrap_or(0) as usize;
+    let mut render = Render::new("Day 12 garden regions", width, height);
+
+    // Number the regions in reading order to keep the colours stable between runs
+    let plots = get_plots(&input)
+        .into_iter()
+        .sorted_by_key(|plot| plot.iter().map(|n| (n.0, n.1)).min())
+        .collect_vec();
+    for (i, plot) in plots.iter().enumerate() {
+        for &&(y, x, _) in plot {
+            render.set(x as usize, y as usize, i as u32 + 1);
+        }
+    }
+    Some(render)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,6 +220,16 @@ EEEEE
         assert_eq!(part1(&parse_input(TEST_INPUT_3)), 1930);
     }
 
+    #[test]
+    fn test_render() {
+        let render = render(TEST_INPUT_1).unwrap();
+        assert_eq!((render.width, render.height), (4, 4));
+        assert_eq!(
+            render.cells,
+            vec![1, 1, 1, 1, 2, 2, 3, 4, 2, 2, 3, 3, 5, 5, 5, 3]
+        );
+    }
+
     #[test]
     fn test_part2() {
         assert_eq!(part2(&parse_input(TEST_INPUT_1)), 80);
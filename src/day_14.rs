@@ -1,3 +1,4 @@
+use crate::report::Render;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use lazy_static::lazy_static;
@@ -159,6 +160,24 @@ fn part2(robots: &Robots) -> i32 {
     maybe_img_steps
 }
 
+fn render_robots(robots: &Robots, len_x: i32, len_y: i32) -> Render {
+    let steps = find_possible_image(robots, len_x, len_y) as i32;
+
+    let mut render = Render::new(
+        &format!("Day 14 tree frame after {} seconds", steps),
+        len_x as usize,
+        len_y as usize,
+    );
+    for r in step_robots(robots, len_x, len_y, steps) {
+        render.set(r.loc.0 as usize, r.loc.1 as usize, 1);
+    }
+    render
+}
+
+pub(crate) fn render(input: &str) -> Option<Render> {
+    Some(render_robots(&parse_input(input), 101, 103))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,4 +205,12 @@ p=9,5 v=-3,-3
     fn test_part2() {
         assert_eq!(find_possible_image(&parse_input(TEST_INPUT), 11, 7), 24);
     }
+
+    #[test]
+    fn test_render() {
+        let render = render_robots(&parse_input(TEST_INPUT), 11, 7);
+        assert_eq!(render.title, "Day 14 tree frame after 24 seconds");
+        assert_eq!((render.width, render.height), (11, 7));
+        assert!(render.cells.contains(&1));
+    }
 }
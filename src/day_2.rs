@@ -1,9 +1,14 @@
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
 
 type Report = Vec<i32>;
 
+const SAFE_STEPS: RangeInclusive<i32> = 1..=3;
+
 fn parse_line(l: &str) -> Report {
     l.split_whitespace().map(|x| x.parse().unwrap()).collect()
 }
@@ -36,6 +41,147 @@ fn is_safe(report: &Report) -> bool {
     (is_increasing(report) || is_decreasing(report)) && diff_in_range(report)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Increasing,
+    Decreasing,
+    Either, // decided by the first non-flat step
+}
+
+#[derive(Debug, Clone)]
+struct SafetyPolicy {
+    steps: RangeInclusive<i32>, // allowed size of a step, regardless of the direction
+    strict: bool,               // non-strict allows flat steps
+    direction: Direction,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            steps: SAFE_STEPS,
+            strict: true,
+            direction: Direction::Either,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Unsafe {
+    DirectionChange { index: usize },
+    WrongDirection { index: usize },
+    Flat { from: usize, to: usize },
+    Jump { size: i32, from: usize, to: usize },
+}
+
+impl Unsafe {
+    // Reason without the location, for grouping
+    fn kind(&self) -> String {
+        match self {
+            Unsafe::DirectionChange { .. } => "direction change".to_string(),
+            Unsafe::WrongDirection { .. } => "wrong direction".to_string(),
+            Unsafe::Flat { .. } => "flat step".to_string(),
+            Unsafe::Jump { size, .. } => format!("jump of {}", size),
+        }
+    }
+}
+
+impl fmt::Display for Unsafe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unsafe::DirectionChange { index } => write!(f, "direction change at index {}", index),
+            Unsafe::WrongDirection { index } => write!(f, "wrong direction at index {}", index),
+            Unsafe::Flat { from, to } => {
+                write!(f, "flat step between indices {} and {}", from, to)
+            }
+            Unsafe::Jump { size, from, to } => {
+                write!(f, "jump of {} between indices {} and {}", size, from, to)
+            }
+        }
+    }
+}
+
+/// The first rule of the policy that the report breaks.
+fn classify(report: &Report, policy: &SafetyPolicy) -> Result<(), Unsafe> {
+    let mut direction = policy.direction;
+
+    for (from, (a, b)) in report.iter().tuple_windows().enumerate() {
+        let to = from + 1;
+        let diff = b - a;
+
+        if diff == 0 {
+            if policy.strict {
+                return Err(Unsafe::Flat { from, to });
+            }
+            continue;
+        }
+
+        let step_direction = if diff > 0 {
+            Direction::Increasing
+        } else {
+            Direction::Decreasing
+        };
+        match direction {
+            Direction::Either => direction = step_direction,
+            d if d != step_direction && policy.direction == Direction::Either => {
+                return Err(Unsafe::DirectionChange { index: to });
+            }
+            d if d != step_direction => return Err(Unsafe::WrongDirection { index: to }),
+            _ => {}
+        }
+
+        if !policy.steps.contains(&diff.abs()) {
+            return Err(Unsafe::Jump {
+                size: diff.abs(),
+                from,
+                to,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Count of reports per unsafe reason, with the safe ones under "safe".
+fn histogram(input: &str, policy: &SafetyPolicy) -> Vec<(String, usize)> {
+    input
+        .lines()
+        .map(parse_line)
+        .map(|report| match classify(&report, policy) {
+            Ok(()) => "safe".to_string(),
+            Err(reason) => reason.kind(),
+        })
+        .counts()
+        .into_iter()
+        .sorted_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)))
+        .collect()
+}
+
+pub(crate) fn diagnostics(input: &str) -> String {
+    let histogram = histogram(input, &SafetyPolicy::default());
+    let width = histogram
+        .iter()
+        .map(|(reason, _)| reason.len())
+        .max()
+        .unwrap_or(0);
+    let max = histogram.iter().map(|(_, count)| *count).max().unwrap_or(1);
+
+    let mut lines = histogram
+        .iter()
+        .map(|(reason, count)| {
+            let bar = "#".repeat((count * 40).div_ceil(max));
+            format!("{:width$}  {:>5}  {}", reason, count, bar)
+        })
+        .collect_vec();
+
+    let rescued = rescued(input, 1);
+    lines.push(format!(
+        "{} unsafe reports rescued by the dampener:",
+        rescued.len()
+    ));
+    lines.extend(rescued.iter().map(|rescue| format!("\t{}", rescue)));
+    lines.join("\n")
+}
+
 #[aoc(day2, part1)]
 fn part1(input: &str) -> i32 {
     input.lines().map(parse_line).filter(is_safe).count() as i32
@@ -59,6 +205,113 @@ fn part2(input: &str) -> i32 {
         .count() as i32
 }
 
+// Longest subsequence where each kept level moves by one of `steps` in the given direction from
+// the previous kept one. Removing the rest is the fewest removals making the report safe.
+fn longest_safe_subsequence(
+    report: &Report,
+    direction: i32,
+    steps: &RangeInclusive<i32>,
+) -> Vec<usize> {
+    // Best (length, end index) of a subsequence ending in the given level value
+    let mut best_by_value: HashMap<i32, (usize, usize)> = HashMap::new();
+    let mut previous: Vec<Option<usize>> = vec![None; report.len()];
+    let mut best: Option<(usize, usize)> = None;
+
+    for (i, level) in report.iter().enumerate() {
+        let from = steps
+            .clone()
+            .flat_map(|step| best_by_value.get(&(level - step * direction)))
+            .max_by_key(|(len, _)| *len);
+
+        let len = from.map_or(1, |(len, _)| len + 1);
+        previous[i] = from.map(|(_, end)| *end);
+
+        let entry = best_by_value.entry(*level).or_insert((len, i));
+        if len > entry.0 {
+            *entry = (len, i);
+        }
+        if best.is_none_or(|(best_len, _)| len > best_len) {
+            best = Some((len, i));
+        }
+    }
+
+    let mut kept = vec![];
+    let mut at = best.map(|(_, end)| end);
+    while let Some(i) = at {
+        kept.push(i);
+        at = previous[i];
+    }
+    kept.reverse();
+    kept
+}
+
+/// The fewest level indices to remove to make the report safe.
+fn levels_to_remove(report: &Report) -> Vec<usize> {
+    [1, -1]
+        .iter()
+        .map(|direction| longest_safe_subsequence(report, *direction, &SAFE_STEPS))
+        .max_by_key(|kept| kept.len())
+        .map(|kept| {
+            let mut keep = vec![false; report.len()];
+            for i in kept {
+                keep[i] = true;
+            }
+            (0..report.len()).filter(|i| !keep[*i]).collect_vec()
+        })
+        .unwrap_or_default()
+}
+
+fn is_safe_with_removals(report: &Report, k: usize) -> Option<Vec<usize>> {
+    Some(levels_to_remove(report)).filter(|removed| removed.len() <= k)
+}
+
+#[derive(Debug, PartialEq)]
+struct Rescue {
+    line: usize,
+    report: Report,
+    removed: Vec<usize>,
+}
+
+impl fmt::Display for Rescue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let levels = self.removed.iter().map(|i| self.report[*i]).join(", ");
+        write!(
+            f,
+            "report {}: {} -> removed index {} (level {})",
+            self.line + 1,
+            self.report.iter().join(" "),
+            self.removed.iter().join(", "),
+            levels
+        )
+    }
+}
+
+/// Unsafe reports that become safe by removing at most k levels.
+fn rescued(input: &str, k: usize) -> Vec<Rescue> {
+    input
+        .lines()
+        .map(parse_line)
+        .enumerate()
+        .filter(|(_, report)| !is_safe(report))
+        .flat_map(|(line, report)| {
+            is_safe_with_removals(&report, k).map(|removed| Rescue {
+                line,
+                report,
+                removed,
+            })
+        })
+        .collect()
+}
+
+#[aoc(day2, part2, dampener)]
+fn part2_dampener(input: &str) -> i32 {
+    input
+        .lines()
+        .map(parse_line)
+        .filter(|report| is_safe_with_removals(report, 1).is_some())
+        .count() as i32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,4 +357,146 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(TEST_INPUT), 4);
     }
+
+    #[test]
+    fn test_classify() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(classify(&vec![7, 6, 4, 2, 1], &policy), Ok(()));
+        assert_eq!(
+            classify(&vec![1, 2, 7, 8, 9], &policy),
+            Err(Unsafe::Jump {
+                size: 5,
+                from: 1,
+                to: 2
+            })
+        );
+        assert_eq!(
+            classify(&vec![1, 3, 2, 4, 5], &policy),
+            Err(Unsafe::DirectionChange { index: 2 })
+        );
+        assert_eq!(
+            classify(&vec![8, 6, 4, 4, 1], &policy),
+            Err(Unsafe::Flat { from: 2, to: 3 })
+        );
+        assert_eq!(
+            classify(&vec![1, 2, 7, 8, 9], &policy)
+                .unwrap_err()
+                .to_string(),
+            "jump of 5 between indices 1 and 2"
+        );
+
+        let relaxed = SafetyPolicy {
+            steps: 1..=5,
+            strict: false,
+            direction: Direction::Decreasing,
+        };
+        assert_eq!(classify(&vec![8, 6, 4, 4, 1], &relaxed), Ok(()));
+        assert_eq!(classify(&vec![9, 7, 6, 2, 1], &relaxed), Ok(()));
+        assert_eq!(
+            classify(&vec![1, 3, 6, 7, 9], &relaxed),
+            Err(Unsafe::WrongDirection { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_classify_matches_is_safe() {
+        let policy = SafetyPolicy::default();
+        for report in (0..4).map(|_| 0..6).multi_cartesian_product() {
+            assert_eq!(classify(&report, &policy).is_ok(), is_safe(&report));
+        }
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(
+            histogram(TEST_INPUT, &SafetyPolicy::default()),
+            vec![
+                ("safe".to_string(), 2),
+                ("direction change".to_string(), 1),
+                ("flat step".to_string(), 1),
+                ("jump of 4".to_string(), 1),
+                ("jump of 5".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_part2_dampener() {
+        assert_eq!(part2_dampener(TEST_INPUT), 4);
+    }
+
+    #[test]
+    fn test_levels_to_remove() {
+        assert!(levels_to_remove(&vec![7, 6, 4, 2, 1]).is_empty());
+        assert_eq!(levels_to_remove(&vec![1, 3, 2, 4, 5]).len(), 1);
+        assert_eq!(levels_to_remove(&vec![8, 6, 4, 4, 1]).len(), 1);
+        assert_eq!(levels_to_remove(&vec![1, 2, 7, 8, 9]).len(), 2);
+        assert_eq!(levels_to_remove(&vec![9, 1, 2, 3]), vec![0]);
+        assert_eq!(levels_to_remove(&vec![1, 2, 3, 9]), vec![3]);
+        assert_eq!(levels_to_remove(&vec![1, 2, 50, 60, 3, 4]), vec![2, 3]);
+        assert!(levels_to_remove(&vec![]).is_empty());
+
+        assert_eq!(is_safe_with_removals(&vec![1, 2, 50, 60, 3, 4], 1), None);
+        assert_eq!(
+            is_safe_with_removals(&vec![1, 2, 50, 60, 3, 4], 2),
+            Some(vec![2, 3])
+        );
+
+        let wide = vec![1, 5, 9, 2];
+        assert_eq!(longest_safe_subsequence(&wide, 1, &SAFE_STEPS), vec![0, 3]);
+        assert_eq!(longest_safe_subsequence(&wide, 1, &(1..=4)), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // Every report of length 5 over a small value range
+        for values in (0..5).map(|_| 0..6).multi_cartesian_product() {
+            let removals = levels_to_remove(&values).len();
+            let brute = (0..=values.len())
+                .find(|k| {
+                    (0..values.len()).combinations(*k).any(|removed| {
+                        let kept = (0..values.len())
+                            .filter(|i| !removed.contains(i))
+                            .map(|i| values[i])
+                            .collect_vec();
+                        is_safe(&kept)
+                    })
+                })
+                .unwrap();
+            assert_eq!(removals, brute, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_rescued() {
+        assert_eq!(
+            rescued(TEST_INPUT, 1),
+            vec![
+                Rescue {
+                    line: 3,
+                    report: vec![1, 3, 2, 4, 5],
+                    removed: vec![1]
+                },
+                Rescue {
+                    line: 4,
+                    report: vec![8, 6, 4, 4, 1],
+                    removed: vec![3]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = diagnostics(TEST_INPUT);
+        let lines = report.lines().collect_vec();
+        assert_eq!(
+            lines[lines.len() - 3..],
+            [
+                "2 unsafe reports rescued by the dampener:",
+                "\treport 4: 1 3 2 4 5 -> removed index 1 (level 3)",
+                "\treport 5: 8 6 4 4 1 -> removed index 3 (level 4)",
+            ]
+        );
+    }
 }
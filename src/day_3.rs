@@ -1,43 +1,524 @@
 use aoc_runner_derive::aoc;
-use lazy_static::lazy_static;
-use regex::Regex;
+use itertools::Itertools;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::Range;
 
-lazy_static! {
-    static ref MULTIPLY: Regex = Regex::new(r"mul\((\d+),(\d+)\)").unwrap();
-    static ref CONDITIONA_MULTIPLY: Regex =
-        Regex::new(r"mul\((\d+),(\d+)\)|do\(\)|don't\(\)").unwrap();
+const CHUNK_SIZE: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String), // letters, '_' and '\''
+    Number(i64),
+    LParen,
+    RParen,
+    Comma,
+    Other,
 }
 
-#[aoc(day3, part1)]
-fn part1(input: &str) -> i32 {
-    let mut total = 0;
-    for cap in MULTIPLY.captures_iter(input) {
-        let a = cap[1].parse::<i32>().unwrap();
-        let b = cap[2].parse::<i32>().unwrap();
-        total += a * b;
-    }
-    total
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>, // byte offsets in the input
 }
 
-#[aoc(day3, part2)]
-fn part2(input: &str) -> i32 {
-    let caps = CONDITIONA_MULTIPLY.captures_iter(input);
-    let mut multiply = true;
-    let mut total = 0;
-    for cap in caps {
-        match &cap[0] {
-            "do()" => multiply = true,
-            "don't()" => multiply = false,
+fn is_word_char(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c == b'\''
+}
+
+fn lex(input: &str) -> Vec<Token> {
+    let bytes = input.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b'(' => TokenKind::LParen,
+            b')' => TokenKind::RParen,
+            b',' => TokenKind::Comma,
+            c if c.is_ascii_digit() => {
+                while i + 1 < bytes.len() && bytes[i + 1].is_ascii_digit() {
+                    i += 1;
+                }
+                // Numbers too large for i64 can't be valid arguments
+                input[start..=i]
+                    .parse()
+                    .map_or(TokenKind::Other, TokenKind::Number)
+            }
+            c if is_word_char(c) => {
+                while i + 1 < bytes.len() && is_word_char(bytes[i + 1]) {
+                    i += 1;
+                }
+                TokenKind::Word(input[start..=i].to_string())
+            }
             _ => {
-                if multiply {
-                    let a = cap[1].parse::<i32>().unwrap();
-                    let b = cap[2].parse::<i32>().unwrap();
-                    total += a * b;
+                // Skip the whole char to keep the spans on char boundaries
+                i += input[i..].chars().next().unwrap().len_utf8() - 1;
+                TokenKind::Other
+            }
+        };
+        i += 1;
+        tokens.push(Token {
+            kind,
+            span: start..i,
+        });
+    }
+
+    tokens
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    enabled: bool,
+    total: i64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            enabled: true,
+            total: 0,
+        }
+    }
+}
+
+// Returns false when the instruction was ignored
+type Exec = fn(&mut State, &[i64]) -> bool;
+
+struct Instruction {
+    name: String,
+    arity: usize,
+    exec: Exec,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TraceEntry {
+    name: String,
+    args: Vec<i64>,
+    span: Range<usize>,
+    executed: bool,
+}
+
+struct Interpreter {
+    instructions: Vec<Instruction>,
+}
+
+/// Why something that starts like a call isn't one.
+#[derive(Debug, Clone, PartialEq)]
+enum NearMiss {
+    WrongBracket(char),
+    TooManyDigits, // for an i64
+    Whitespace,
+    MissingArgument,
+    ExtraArgument,
+    Unexpected(char),
+    Unterminated,
+}
+
+impl NearMiss {
+    // Reason without the character, for grouping
+    fn kind(&self) -> &'static str {
+        match self {
+            NearMiss::WrongBracket(_) => "wrong bracket",
+            NearMiss::TooManyDigits => "too many digits",
+            NearMiss::Whitespace => "whitespace",
+            NearMiss::MissingArgument => "missing argument",
+            NearMiss::ExtraArgument => "extra argument",
+            NearMiss::Unexpected(_) => "unexpected character",
+            NearMiss::Unterminated => "unterminated",
+        }
+    }
+}
+
+impl fmt::Display for NearMiss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NearMiss::WrongBracket(c) | NearMiss::Unexpected(c) => {
+                write!(f, "{} {:?}", self.kind(), c)
+            }
+            _ => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Diagnostic {
+    name: String,
+    span: Range<usize>, // up to where the call went wrong
+    reason: NearMiss,
+}
+
+fn mul(state: &mut State, args: &[i64]) -> bool {
+    if state.enabled {
+        state.total += args[0] * args[1];
+    }
+    state.enabled
+}
+
+fn enable(state: &mut State, _: &[i64]) -> bool {
+    state.enabled = true;
+    true
+}
+
+fn disable(state: &mut State, _: &[i64]) -> bool {
+    state.enabled = false;
+    true
+}
+
+impl Interpreter {
+    fn new() -> Self {
+        Interpreter {
+            instructions: vec![],
+        }
+    }
+
+    fn with(mut self, name: &str, arity: usize, exec: Exec) -> Self {
+        self.instructions.push(Instruction {
+            name: name.to_string(),
+            arity,
+            exec,
+        });
+        self
+    }
+
+    fn part1() -> Self {
+        Interpreter::new().with("mul", 2, mul)
+    }
+
+    fn part2() -> Self {
+        Interpreter::part1()
+            .with("do", 0, enable)
+            .with("don't", 0, disable)
+    }
+
+    // Corrupted memory glues garbage in front of the names, so the longest matching suffix wins
+    fn lookup(&self, word: &str) -> Option<&Instruction> {
+        self.instructions
+            .iter()
+            .filter(|instruction| word.ends_with(&instruction.name))
+            .max_by_key(|instruction| instruction.name.len())
+    }
+
+    /// `name(arg,...)` starting at the token index, with the exact arity of the instruction.
+    fn parse_call(&self, tokens: &[Token], at: usize) -> Option<(&Instruction, Vec<i64>, usize)> {
+        let TokenKind::Word(word) = &tokens[at].kind else {
+            return None;
+        };
+        let instruction = self.lookup(word)?;
+
+        let is = |i: usize, kind: &TokenKind| tokens.get(i).is_some_and(|t| t.kind == *kind);
+
+        let mut i = at + 1;
+        if !is(i, &TokenKind::LParen) {
+            return None;
+        }
+        i += 1;
+
+        let mut args = vec![];
+        for n in 0..instruction.arity {
+            if n > 0 {
+                if !is(i, &TokenKind::Comma) {
+                    return None;
+                }
+                i += 1;
+            }
+            match tokens.get(i) {
+                Some(Token {
+                    kind: TokenKind::Number(value),
+                    ..
+                }) => args.push(*value),
+                _ => return None,
+            }
+            i += 1;
+        }
+
+        if !is(i, &TokenKind::RParen) {
+            return None;
+        }
+        Some((instruction, args, i))
+    }
+
+    /// Executes the calls starting before token `limit`, `offset` is added to the spans.
+    fn execute(
+        &self,
+        tokens: &[Token],
+        limit: usize,
+        offset: usize,
+        state: &mut State,
+        trace: &mut Vec<TraceEntry>,
+    ) {
+        let mut at = 0;
+        while at < limit {
+            match self.parse_call(tokens, at) {
+                Some((instruction, args, end)) => {
+                    let word_end = offset + tokens[at].span.end;
+                    let executed = (instruction.exec)(state, &args);
+                    trace.push(TraceEntry {
+                        name: instruction.name.clone(),
+                        args,
+                        span: word_end - instruction.name.len()..offset + tokens[end].span.end,
+                        executed,
+                    });
+                    at = end + 1;
+                }
+                None => at += 1,
+            }
+        }
+    }
+
+    fn run(&self, input: &str) -> (State, Vec<TraceEntry>) {
+        let tokens = lex(input);
+        let mut state = State::default();
+        let mut trace = vec![];
+        self.execute(&tokens, tokens.len(), 0, &mut state, &mut trace);
+        (state, trace)
+    }
+
+    /// Index of the token starting a call that more input could still complete.
+    fn incomplete_call(&self, tokens: &[Token]) -> Option<usize> {
+        let at = tokens
+            .iter()
+            .rposition(|t| matches!(t.kind, TokenKind::Word(_)))?;
+        let tail = &tokens[at + 1..];
+        if tail.is_empty() {
+            // The word itself may go on
+            return Some(at);
+        }
+
+        let TokenKind::Word(word) = &tokens[at].kind else {
+            unreachable!()
+        };
+        let instruction = self.lookup(word)?;
+        let mut numbers = 0;
+        for (i, token) in tail.iter().enumerate() {
+            match token.kind {
+                TokenKind::LParen if i == 0 => {}
+                TokenKind::Number(_) if i % 2 == 1 && numbers < instruction.arity => numbers += 1,
+                TokenKind::Comma if i > 0 && i % 2 == 0 && numbers < instruction.arity => {}
+                _ => return None,
+            }
+        }
+        Some(at)
+    }
+
+    /// Checks the text after an instruction name against the grammar, character by character.
+    /// Only a name followed by some kind of opening bracket counts as an attempted call.
+    fn near_miss(
+        &self,
+        input: &str,
+        instruction: &Instruction,
+        name_end: usize,
+    ) -> Option<(NearMiss, usize)> {
+        let bytes = input.as_bytes();
+        let at = |i: usize| input[i..].chars().next();
+        let skip_whitespace = |i: &mut usize| {
+            let start = *i;
+            while bytes.get(*i).is_some_and(|c| c.is_ascii_whitespace()) {
+                *i += 1;
+            }
+            *i > start
+        };
+        let stop = |reason: NearMiss, i: usize| {
+            let end = at(i).map_or(i, |c| i + c.len_utf8());
+            Some((reason, end))
+        };
+
+        let mut i = name_end;
+        let mut whitespace = skip_whitespace(&mut i);
+        match at(i) {
+            Some('(') => i += 1,
+            Some(c @ ('[' | '{' | '<')) => return stop(NearMiss::WrongBracket(c), i),
+            _ => return None,
+        }
+
+        for n in 0..instruction.arity {
+            whitespace |= skip_whitespace(&mut i);
+            if n > 0 {
+                match at(i) {
+                    Some(',') => i += 1,
+                    Some(')' | ']' | '}' | '>') => return stop(NearMiss::MissingArgument, i),
+                    Some(c) => return stop(NearMiss::Unexpected(c), i),
+                    None => return stop(NearMiss::Unterminated, i),
                 }
+                whitespace |= skip_whitespace(&mut i);
+            }
+
+            let start = i;
+            while bytes.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                i += 1;
+            }
+            if start == i {
+                return match at(i) {
+                    Some(',' | ')' | ']' | '}' | '>') => stop(NearMiss::MissingArgument, i),
+                    Some(c) => stop(NearMiss::Unexpected(c), i),
+                    None => stop(NearMiss::Unterminated, i),
+                };
+            }
+            if input[start..i].parse::<i64>().is_err() {
+                return Some((NearMiss::TooManyDigits, i));
+            }
+        }
+
+        whitespace |= skip_whitespace(&mut i);
+        match at(i) {
+            Some(')') => i += 1,
+            Some(c @ (']' | '}' | '>')) => return stop(NearMiss::WrongBracket(c), i),
+            Some(',' | '0'..='9') => return stop(NearMiss::ExtraArgument, i),
+            Some(c) => return stop(NearMiss::Unexpected(c), i),
+            None => return stop(NearMiss::Unterminated, i),
+        }
+        whitespace.then_some((NearMiss::Whitespace, i))
+    }
+
+    /// Attempted calls that the grammar rejects, in input order.
+    fn near_misses(&self, input: &str) -> Vec<Diagnostic> {
+        lex(input)
+            .iter()
+            .filter_map(|token| {
+                let TokenKind::Word(word) = &token.kind else {
+                    return None;
+                };
+                let instruction = self.lookup(word)?;
+                let (reason, end) = self.near_miss(input, instruction, token.span.end)?;
+                Some(Diagnostic {
+                    name: instruction.name.clone(),
+                    span: token.span.end - instruction.name.len()..end,
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Same as `run`, but reads the input in chunks of `chunk_size` bytes.
+    fn scan<R: Read>(&self, mut reader: R, chunk_size: usize) -> io::Result<State> {
+        let mut scanner = Scanner::new(self);
+        let mut chunk = vec![0; chunk_size];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => scanner.feed(&chunk[..n])?,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
             }
+            // The input may not fit in memory, neither does its trace
+            scanner.trace.clear();
+        }
+        Ok(scanner.finish()?.0)
+    }
+}
+
+/// Runs the input as it arrives, holding back whatever may still become a call.
+struct Scanner<'a> {
+    interpreter: &'a Interpreter,
+    state: State,
+    trace: Vec<TraceEntry>,
+    pending: Vec<u8>,
+    offset: usize, // of pending[0] in the whole input
+}
+
+impl<'a> Scanner<'a> {
+    fn new(interpreter: &'a Interpreter) -> Self {
+        Scanner {
+            interpreter,
+            state: State::default(),
+            trace: vec![],
+            pending: vec![],
+            offset: 0,
         }
     }
-    total
+
+    fn feed(&mut self, chunk: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(chunk);
+        self.process(false)
+    }
+
+    fn finish(mut self) -> io::Result<(State, Vec<TraceEntry>)> {
+        self.process(true)?;
+        Ok((self.state, self.trace))
+    }
+
+    fn process(&mut self, last: bool) -> io::Result<()> {
+        let text = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text,
+            // A char split between chunks is completed by the next one
+            Err(e) if e.error_len().is_none() && !last => {
+                std::str::from_utf8(&self.pending[..e.valid_up_to()]).unwrap()
+            }
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+
+        let tokens = lex(text);
+        let (limit, cut) = match self.interpreter.incomplete_call(&tokens) {
+            Some(at) if !last => {
+                // Only the end of a long word can still be the name of an instruction
+                let longest = self.interpreter.instructions.iter().map(|i| i.name.len());
+                let span = &tokens[at].span;
+                let start = span.end.saturating_sub(longest.max().unwrap_or(0));
+                (at, span.start.max(start))
+            }
+            _ => (tokens.len(), text.len()),
+        };
+
+        self.interpreter.execute(
+            &tokens,
+            limit,
+            self.offset,
+            &mut self.state,
+            &mut self.trace,
+        );
+        self.pending.drain(..cut);
+        self.offset += cut;
+        Ok(())
+    }
+}
+
+pub(crate) fn diagnostics(input: &str) -> String {
+    let near_misses = Interpreter::part2().near_misses(input);
+    let counts = near_misses
+        .iter()
+        .map(|d| d.reason.kind())
+        .counts()
+        .into_iter()
+        .sorted_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)))
+        .map(|(kind, count)| format!("{:>5}  {}", count, kind));
+
+    near_misses
+        .iter()
+        .map(|d| {
+            format!(
+                "{:>8}  {:<20}  {}",
+                d.span.start,
+                format!("{:?}", &input[d.span.clone()]),
+                d.reason
+            )
+        })
+        .chain(counts)
+        .join("\n")
+}
+
+#[aoc(day3, part1)]
+fn part1(input: &str) -> i64 {
+    Interpreter::part1().run(input).0.total
+}
+
+#[aoc(day3, part2)]
+fn part2(input: &str) -> i64 {
+    Interpreter::part2().run(input).0.total
+}
+
+#[aoc(day3, part1, streaming)]
+fn part1_streaming(input: &str) -> io::Result<i64> {
+    Ok(Interpreter::part1()
+        .scan(input.as_bytes(), CHUNK_SIZE)?
+        .total)
+}
+
+#[aoc(day3, part2, streaming)]
+fn part2_streaming(input: &str) -> io::Result<i64> {
+    Ok(Interpreter::part2()
+        .scan(input.as_bytes(), CHUNK_SIZE)?
+        .total)
 }
 
 #[cfg(test)]
@@ -59,4 +540,248 @@ mod tests {
     fn test_part2() {
         assert_eq!(part2(CONDITIONAL_TEST_INPUT), 48);
     }
+
+    #[test]
+    fn test_lex() {
+        let kinds = |input| lex(input).into_iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds("xmul(2,40)"),
+            vec![
+                TokenKind::Word("xmul".to_string()),
+                TokenKind::LParen,
+                TokenKind::Number(2),
+                TokenKind::Comma,
+                TokenKind::Number(40),
+                TokenKind::RParen,
+            ]
+        );
+        assert_eq!(
+            lex("é don't"),
+            vec![
+                Token {
+                    kind: TokenKind::Other,
+                    span: 0..2
+                },
+                Token {
+                    kind: TokenKind::Other,
+                    span: 2..3
+                },
+                Token {
+                    kind: TokenKind::Word("don't".to_string()),
+                    span: 3..8
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let (state, trace) = Interpreter::part2().run(CONDITIONAL_TEST_INPUT);
+        assert_eq!(state.total, 48);
+        assert!(state.enabled);
+
+        let summary = trace
+            .iter()
+            .map(|t| {
+                (
+                    t.name.as_str(),
+                    &CONDITIONAL_TEST_INPUT[t.span.clone()],
+                    t.executed,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("mul", "mul(2,4)", true),
+                ("don't", "don't()", true),
+                ("mul", "mul(5,5)", false),
+                ("mul", "mul(11,8)", false),
+                ("do", "do()", true),
+                ("mul", "mul(8,5)", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_regex() {
+        use regex::Regex;
+        let reference = Regex::new(r"mul\((\d+),(\d+)\)|do\(\)|don't\(\)").unwrap();
+
+        let fragments = [
+            "mul(",
+            "mul",
+            "mul(1,23)",
+            "do",
+            "do()",
+            "don't",
+            "don't()",
+            "n't",
+            "(",
+            ")",
+            ",",
+            "1",
+            "23",
+            "x",
+            " ",
+            "_",
+            "é",
+        ];
+        let mut seed: u64 = 17;
+        let mut matches = 0;
+        for _ in 0..2000 {
+            let input = (0..20)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                    fragments[(seed >> 33) as usize % fragments.len()]
+                })
+                .collect::<String>();
+
+            let expected = reference
+                .find_iter(&input)
+                .map(|m| m.range())
+                .collect::<Vec<_>>();
+            let (_, trace) = Interpreter::part2().run(&input);
+            let spans = trace.into_iter().map(|t| t.span).collect::<Vec<_>>();
+            assert_eq!(spans, expected, "{}", input);
+            matches += spans.len();
+        }
+        assert!(matches > 1000);
+    }
+
+    #[test]
+    fn test_user_defined_instruction() {
+        fn add(state: &mut State, args: &[i64]) -> bool {
+            state.total += args.iter().sum::<i64>();
+            true
+        }
+        let interpreter = Interpreter::part1().with("add", 3, add);
+        let (state, trace) = interpreter.run("mul(2,3)add(1,2,3)add(1,2)mul(4)");
+        assert_eq!(state.total, 12);
+        assert_eq!(trace.len(), 2);
+    }
+
+    #[test]
+    fn test_scan_every_split() {
+        let long = format!("{}mul(1,2)", "x".repeat(100));
+        let inputs = [
+            TEST_INPUT,
+            CONDITIONAL_TEST_INPUT,
+            "é mul(123,4)dón't()mul(5,6)do()mul(7,8)",
+            &long,
+        ];
+        for interpreter in [Interpreter::part1(), Interpreter::part2()] {
+            for input in inputs {
+                let expected = interpreter.run(input);
+                let bytes = input.as_bytes();
+                for split in 0..=bytes.len() {
+                    let mut scanner = Scanner::new(&interpreter);
+                    scanner.feed(&bytes[..split]).unwrap();
+                    scanner.feed(&bytes[split..]).unwrap();
+                    assert_eq!(
+                        scanner.finish().unwrap(),
+                        expected,
+                        "{} at {}",
+                        input,
+                        split
+                    );
+                }
+                for chunk_size in 1..=bytes.len() {
+                    let state = interpreter.scan(bytes, chunk_size).unwrap();
+                    assert_eq!(state, expected.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_holds_back_little() {
+        let interpreter = Interpreter::part2();
+        let mut scanner = Scanner::new(&interpreter);
+        scanner.feed(b"mul(2,4)xyzzydon").unwrap();
+        assert_eq!(scanner.pending, b"zydon");
+        scanner.feed(b"'t(").unwrap();
+        assert_eq!(scanner.pending, b"don't(");
+        scanner.feed(b"!mul(1,").unwrap();
+        assert_eq!(scanner.pending, b"mul(1,");
+        scanner.feed(b"2,").unwrap();
+        assert!(scanner.pending.is_empty());
+    }
+
+    #[test]
+    fn test_scan_invalid_utf8() {
+        let interpreter = Interpreter::part1();
+        assert!(interpreter.scan(&b"mul(1,2)\xff"[..], 4).is_err());
+        assert!(interpreter.scan(&b"mul(1,2)\xc3"[..], 4).is_err());
+    }
+
+    #[test]
+    fn test_streaming() {
+        assert_eq!(part1_streaming(TEST_INPUT).unwrap(), 161);
+        assert_eq!(part2_streaming(CONDITIONAL_TEST_INPUT).unwrap(), 48);
+    }
+
+    #[test]
+    fn test_near_misses() {
+        let near_misses = Interpreter::part1().near_misses(TEST_INPUT);
+        assert_eq!(
+            near_misses,
+            vec![
+                Diagnostic {
+                    name: "mul".to_string(),
+                    span: 11..15,
+                    reason: NearMiss::WrongBracket('['),
+                },
+                Diagnostic {
+                    name: "mul".to_string(),
+                    span: 38..48,
+                    reason: NearMiss::WrongBracket(']'),
+                },
+            ]
+        );
+        assert_eq!(&TEST_INPUT[38..48], "mul(32,64]");
+
+        let reasons = |input| {
+            Interpreter::part2()
+                .near_misses(input)
+                .into_iter()
+                .map(|d| (d.span.start, d.reason))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(reasons("mul ( 2 , 4 )"), vec![(0, NearMiss::Whitespace)]);
+        assert_eq!(reasons("xdon't ()"), vec![(1, NearMiss::Whitespace)]);
+        assert_eq!(
+            reasons("mul(12345678901234567890,1)mul(2)mul(,3)mul(1,2,3)"),
+            vec![
+                (0, NearMiss::TooManyDigits),
+                (27, NearMiss::MissingArgument),
+                (33, NearMiss::MissingArgument),
+                (40, NearMiss::ExtraArgument),
+            ]
+        );
+        assert_eq!(
+            reasons("mul(-1,2)do(2)mul(4,5"),
+            vec![
+                (0, NearMiss::Unexpected('-')),
+                (9, NearMiss::ExtraArgument),
+                (14, NearMiss::Unterminated),
+            ]
+        );
+        assert!(reasons("mul(1,2)do()mul don't_mul*").is_empty());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = diagnostics("mul[3,7]mul(32,64]mul ( 2 , 4 )");
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            vec![
+                "       0  \"mul[\"                wrong bracket '['",
+                "       8  \"mul(32,64]\"          wrong bracket ']'",
+                "      18  \"mul ( 2 , 4 )\"       whitespace",
+                "    2  wrong bracket",
+                "    1  whitespace",
+            ]
+        );
+    }
 }
@@ -1,4 +1,7 @@
+use aho_corasick::{AhoCorasick, BuildError};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::error::Error;
+use std::fmt;
 
 struct Grid {
     len_y: usize,
@@ -6,62 +9,221 @@ struct Grid {
     data: Vec<Vec<char>>,
 }
 type YX = (i32, i32);
-type Coords = [YX; 4];
-
-fn get_xmas_coords(p: &YX) -> Vec<Coords> {
-    let deltas: Vec<YX> = vec![
-        (0, 1),   // →
-        (1, 1),   // ↘
-        (1, 0),   // ↓
-        (1, -1),  // ↙
-        (0, -1),  // ←
-        (-1, -1), // ↖
-        (-1, 0),  // ↑
-        (-1, 1),  // ↗
-    ];
-
-    deltas
-        .iter()
-        .map(|d| {
-            [
-                (p.0, p.1),
-                (p.0 + d.0, p.1 + d.1),
-                (p.0 + 2 * d.0, p.1 + 2 * d.1),
-                (p.0 + 3 * d.0, p.1 + 3 * d.1),
-            ]
-        })
-        .collect()
-}
-
-fn get_corner_coords(p: &YX) -> Vec<Coords> {
-    let (y, x) = p;
-    vec![
-        // Coords of expected M M S S
-        [
-            (y - 1, x - 1), // ↓
-            (y - 1, x + 1), // M M
-            (y + 1, x - 1), //  A
-            (y + 1, x + 1), // S S
-        ],
-        [
-            (y - 1, x - 1), // →
-            (y + 1, x - 1), // M S
-            (y - 1, x + 1), //  A
-            (y + 1, x + 1), // M S
-        ],
-        [
-            (y + 1, x - 1), // ↑
-            (y + 1, x + 1), // S S
-            (y - 1, x - 1), //  A
-            (y - 1, x + 1), // M M
-        ],
-        [
-            (y - 1, x + 1), // ←
-            (y + 1, x + 1), // S M
-            (y - 1, x - 1), //  A
-            (y + 1, x - 1), // S M
-        ],
-    ]
+
+const ORTHOGONAL: [YX; 4] = [
+    (0, 1),  // →
+    (1, 0),  // ↓
+    (0, -1), // ←
+    (-1, 0), // ↑
+];
+
+const ALL_DIRECTIONS: [YX; 8] = [
+    (0, 1),   // →
+    (1, 1),   // ↘
+    (1, 0),   // ↓
+    (1, -1),  // ↙
+    (0, -1),  // ←
+    (-1, -1), // ↖
+    (-1, 0),  // ↑
+    (-1, 1),  // ↗
+];
+
+/// Where words are read.
+enum Directions<'a> {
+    Orthogonal,
+    All,
+    Custom(&'a [YX]),
+}
+
+impl Directions<'_> {
+    fn steps(&self) -> &[YX] {
+        match self {
+            Directions::Orthogonal => &ORTHOGONAL,
+            Directions::All => &ALL_DIRECTIONS,
+            Directions::Custom(steps) => steps,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum SearchError {
+    EmptyWord,
+    Build(BuildError),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchError::EmptyWord => write!(f, "Cannot search for an empty word"),
+            SearchError::Build(e) => write!(f, "Cannot search for the words: {}", e),
+        }
+    }
+}
+
+impl Error for SearchError {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Match<'a> {
+    word: &'a str,
+    start: YX,
+    direction: YX,
+}
+
+/// Every cell of the grid, read from the border in the given direction.
+fn rays(grid: &Grid, direction: YX) -> Vec<Vec<YX>> {
+    let inside = |p: &YX| get_grid_char(grid, p).is_some();
+    let mut rays = vec![];
+    for y in 0..grid.len_y {
+        for x in 0..grid.data[y].len() {
+            let start = (y as i32, x as i32);
+            if inside(&(start.0 - direction.0, start.1 - direction.1)) {
+                continue;
+            }
+            let mut ray = vec![];
+            let mut p = start;
+            while inside(&p) {
+                ray.push(p);
+                p = (p.0 + direction.0, p.1 + direction.1);
+            }
+            rays.push(ray);
+        }
+    }
+    rays
+}
+
+/// All the words in all the directions, overlaps included. Each line of the grid is scanned once
+/// per direction for the whole dictionary.
+fn search<'a>(
+    grid: &Grid,
+    words: &[&'a str],
+    directions: &Directions,
+) -> Result<Vec<Match<'a>>, SearchError> {
+    if words.iter().any(|word| word.is_empty()) {
+        return Err(SearchError::EmptyWord);
+    }
+    let automaton = AhoCorasick::new(words).map_err(SearchError::Build)?;
+    let mut matches = vec![];
+
+    for &direction in directions.steps() {
+        for ray in rays(grid, direction) {
+            let mut line = String::new();
+            let mut offsets = vec![]; // byte offset of every char in line
+            for p in &ray {
+                offsets.push(line.len());
+                line.push(get_grid_char(grid, p).unwrap());
+            }
+
+            for found in automaton.find_overlapping_iter(&line) {
+                let i = offsets.binary_search(&found.start()).unwrap();
+                matches.push(Match {
+                    word: words[found.pattern().as_usize()],
+                    start: ray[i],
+                    direction,
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// A small 2D pattern, `.` matches anything.
+#[derive(Debug, Clone, PartialEq)]
+struct Template {
+    height: i32,
+    width: i32,
+    cells: Vec<(YX, char)>, // sorted
+}
+
+impl Template {
+    fn parse(text: &str) -> Template {
+        let lines = text.lines().collect::<Vec<_>>();
+        let mut cells = vec![];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c != '.' {
+                    cells.push(((y as i32, x as i32), c));
+                }
+            }
+        }
+        Template {
+            height: lines.len() as i32,
+            width: lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as i32,
+            cells,
+        }
+    }
+
+    fn map(&self, height: i32, width: i32, f: impl Fn(YX) -> YX) -> Template {
+        let mut cells = self
+            .cells
+            .iter()
+            .map(|&(p, c)| (f(p), c))
+            .collect::<Vec<_>>();
+        cells.sort();
+        Template {
+            height,
+            width,
+            cells,
+        }
+    }
+
+    // Clockwise
+    fn rotate(&self) -> Template {
+        self.map(self.width, self.height, |(y, x)| (x, self.height - 1 - y))
+    }
+
+    fn reflect(&self) -> Template {
+        self.map(self.height, self.width, |(y, x)| (y, self.width - 1 - x))
+    }
+
+    /// The four rotations of the template and of its mirror image. Symmetric templates give the
+    /// same variant several times unless `dedup` is set.
+    fn variants(&self, dedup: bool) -> Vec<Template> {
+        let mut variants: Vec<Template> = vec![];
+        for mut variant in [self.map(self.height, self.width, |p| p), self.reflect()] {
+            for _ in 0..4 {
+                let next = variant.rotate();
+                if !dedup || !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                variant = next;
+            }
+        }
+        variants
+    }
+
+    fn matches_at(&self, grid: &Grid, origin: YX) -> bool {
+        self.cells
+            .iter()
+            .all(|&((y, x), c)| get_grid_char(grid, &(origin.0 + y, origin.1 + x)) == Some(c))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TemplateMatch {
+    variant: usize, // index in the searched templates
+    origin: YX,     // grid position of the top left corner
+}
+
+fn find_templates(grid: &Grid, templates: &[Template]) -> Vec<TemplateMatch> {
+    let mut matches = vec![];
+    for y in 0..grid.len_y as i32 {
+        for x in 0..grid.len_x as i32 {
+            for (variant, template) in templates.iter().enumerate() {
+                if template.matches_at(grid, (y, x)) {
+                    matches.push(TemplateMatch {
+                        variant,
+                        origin: (y, x),
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn count_templates(grid: &Grid, templates: &[Template]) -> usize {
+    find_templates(grid, templates).len()
 }
 
 fn get_grid_char(grid: &Grid, p: &YX) -> Option<char> {
@@ -84,54 +246,38 @@ fn parse_input(input: &str) -> Grid {
 }
 
 #[aoc(day4, part1)]
-fn part1(grid: &Grid) -> i32 {
-    let mut count = 0;
-    for y in 0..grid.len_y {
-        for x in 0..grid.len_x {
-            let p = (y as i32, x as i32);
-            if get_grid_char(grid, &p) != Some('X') {
-                continue;
-            }
-            for coord in get_xmas_coords(&p) {
-                if let (Some(m), Some(a), Some(s)) = (
-                    get_grid_char(grid, &coord[1]),
-                    get_grid_char(grid, &coord[2]),
-                    get_grid_char(grid, &coord[3]),
-                ) {
-                    if m == 'M' && a == 'A' && s == 'S' {
-                        count += 1;
-                    }
-                }
-            }
-        }
-    }
-    count
+fn part1(grid: &Grid) -> Result<i32, SearchError> {
+    Ok(search(grid, &["XMAS"], &Directions::All)?.len() as i32)
 }
 
 #[aoc(day4, part2)]
 fn part2(grid: &Grid) -> i32 {
-    let mut count = 0;
-    for y in 0..grid.len_y {
-        for x in 0..grid.len_x {
-            let p = (y as i32, x as i32);
-            match get_grid_char(grid, &p) {
-                Some('A') => {
-                    let rotations = get_corner_coords(&p);
-                    if rotations.iter().any(|coord| {
-                        get_grid_char(grid, &coord[0]) == Some('M')
-                            && get_grid_char(grid, &coord[1]) == Some('M')
-                            && get_grid_char(grid, &coord[2]) == Some('S')
-                            && get_grid_char(grid, &coord[3]) == Some('S')
-                    }) {
-                        count += 1
-                    }
-                }
-                _ => continue,
-            }
+    let x_mas = Template::parse("M.S\n.A.\nM.S");
+    count_templates(grid, &x_mas.variants(true)) as i32
+}
+
+/// How many XMAS read along the lines of the grid and how many on the diagonals, then in each
+/// direction.
+pub(crate) fn diagnostics(input: &str) -> String {
+    let grid = parse_input(input);
+    let count = |directions: &Directions| search(&grid, &["XMAS"], directions).map(|m| m.len());
+    let (orthogonal, all) = match (count(&Directions::Orthogonal), count(&Directions::All)) {
+        (Ok(orthogonal), Ok(all)) => (orthogonal, all),
+        (Err(e), _) | (_, Err(e)) => return e.to_string(),
+    };
+    let mut lines = vec![format!(
+        "{} XMAS, {} orthogonal and {} diagonal",
+        all,
+        orthogonal,
+        all - orthogonal
+    )];
+    for direction in &ALL_DIRECTIONS {
+        match count(&Directions::Custom(std::slice::from_ref(direction))) {
+            Ok(n) => lines.push(format!("\t{:?}: {}", direction, n)),
+            Err(e) => return e.to_string(),
         }
     }
-
-    count
+    lines.join("\n")
 }
 
 #[cfg(test)]
@@ -151,18 +297,137 @@ MXMXAXMASX
 ";
 
     #[test]
-    fn test_get_xmas_coords() {
-        let c = get_xmas_coords(&(0, 0));
-        assert_eq!(c.len(), 8);
+    fn test_search() {
+        let grid = parse_input("ABC\nDEF\nGHI\n");
+        let matches = search(&grid, &["AE", "EI", "AEI", "FC", "XY"], &Directions::All).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                Match {
+                    word: "AE",
+                    start: (0, 0),
+                    direction: (1, 1)
+                },
+                Match {
+                    word: "AEI",
+                    start: (0, 0),
+                    direction: (1, 1)
+                },
+                Match {
+                    word: "EI",
+                    start: (1, 1),
+                    direction: (1, 1)
+                },
+                Match {
+                    word: "FC",
+                    start: (1, 2),
+                    direction: (-1, 0)
+                },
+            ]
+        );
+
+        assert!(search(&grid, &["AE"], &Directions::Orthogonal)
+            .unwrap()
+            .is_empty());
+        // Knight moves
+        let matches = search(&grid, &["AF", "AH"], &Directions::Custom(&[(1, 2), (2, 1)])).unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.word, m.start))
+                .collect::<Vec<_>>(),
+            vec![("AF", (0, 0)), ("AH", (0, 0))]
+        );
+
+        assert!(matches!(
+            search(&grid, &["AE", ""], &Directions::All),
+            Err(SearchError::EmptyWord)
+        ));
+        // Standing still reads nothing
+        assert!(search(&grid, &["A"], &Directions::Custom(&[(0, 0)]))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_words() {
+        let grid = parse_input(TEST_INPUT);
+        let matches = search(&grid, &["XMAS", "SAMX", "MAS"], &Directions::Orthogonal).unwrap();
+        let count = |word| matches.iter().filter(|m| m.word == word).count();
+        // A horizontal or vertical XMAS is a SAMX read the other way
+        assert_eq!(count("XMAS"), count("SAMX"));
+        assert_eq!(
+            count("XMAS"),
+            search(&grid, &["XMAS"], &Directions::All)
+                .unwrap()
+                .iter()
+                .filter(|m| m.direction.0 == 0 || m.direction.1 == 0)
+                .count()
+        );
+        assert!(matches.contains(&Match {
+            word: "XMAS",
+            start: (0, 5),
+            direction: (0, 1)
+        }));
     }
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&parse_input(TEST_INPUT)), 18);
+        assert_eq!(part1(&parse_input(TEST_INPUT)).unwrap(), 18);
     }
 
     #[test]
     fn test_part2() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 9);
     }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = diagnostics(TEST_INPUT.trim_end());
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "18 XMAS, 8 orthogonal and 10 diagonal");
+        assert_eq!(lines[1], "\t(0, 1): 3");
+        assert_eq!(lines.len(), 9);
+    }
+
+    #[test]
+    fn test_template_variants() {
+        let x_mas = Template::parse("M.S\n.A.\nM.S");
+        assert_eq!(x_mas.variants(false).len(), 8);
+        assert_eq!(x_mas.variants(true).len(), 4);
+        assert_eq!(x_mas.rotate(), Template::parse("M.M\n.A.\nS.S"));
+
+        let l = Template::parse("X.\nXX\nX.");
+        assert_eq!(l.rotate(), Template::parse("XXX\n.X."));
+        assert_eq!(l.reflect(), Template::parse(".X\nXX\n.X"));
+        assert_eq!(l.variants(true).len(), 4);
+
+        let skew = Template::parse("AB.\n.CD");
+        assert_eq!(skew.variants(true).len(), 8);
+        assert_eq!(Template::parse("A").variants(true).len(), 1);
+    }
+
+    #[test]
+    fn test_find_templates() {
+        let grid = parse_input(".M.\nMAS\n.S.\n");
+        let plus = Template::parse(".M.\nMAS\n.S.").variants(true);
+        assert_eq!(plus.len(), 4);
+        assert_eq!(
+            find_templates(&grid, &plus),
+            vec![TemplateMatch {
+                variant: 0,
+                origin: (0, 0)
+            }]
+        );
+
+        let grid = parse_input(TEST_INPUT);
+        let x_mas = Template::parse("M.S\n.A.\nM.S");
+        assert_eq!(count_templates(&grid, &x_mas.variants(false)), 18);
+        assert!(
+            find_templates(&grid, &x_mas.variants(true)).contains(&TemplateMatch {
+                variant: 0,
+                origin: (0, 1)
+            })
+        );
+    }
 }
@@ -1,5 +1,9 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
 
 type Rule = (i32, i32);
 type Rules = HashSet<Rule>;
@@ -42,12 +46,140 @@ fn update_is_correct(update: &Update, rules: &Rules) -> bool {
     })
 }
 
-#[aoc(day5, part1)]
-fn part1(input: &Input) -> i32 {
-    let correct = input
+/// Position of every page in the order the rules define, only when there is a rule between every
+/// two pages. Then a page before another always has a rule saying so, and checking an update is
+/// checking its ranks increase.
+struct Ranking(HashMap<i32, usize>);
+
+impl Ranking {
+    fn new(rules: &Rules) -> Option<Ranking> {
+        let pages = pages(rules);
+        if rules.len() != pages.len() * pages.len().saturating_sub(1) / 2 {
+            return None;
+        }
+        let order = global_order(rules)?;
+        Some(Ranking(
+            order.into_iter().enumerate().map(|(i, p)| (p, i)).collect(),
+        ))
+    }
+
+    fn update_is_correct(&self, update: &Update) -> bool {
+        // Pages without rules can go anywhere
+        let ranks = update.iter().filter_map(|p| self.0.get(p));
+        ranks.tuple_windows().all(|(a, b)| a < b)
+    }
+}
+
+fn pages(rules: &Rules) -> Vec<i32> {
+    rules
+        .iter()
+        .flat_map(|&(l, r)| [l, r])
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Every (l, r) such that l comes before r through a chain of rules.
+fn closure(rules: &Rules) -> Rules {
+    let successors: HashMap<i32, Vec<i32>> = rules.iter().copied().into_group_map();
+    let mut closure = Rules::new();
+    for (&from, next) in &successors {
+        let mut stack = next.clone();
+        while let Some(page) = stack.pop() {
+            if closure.insert((from, page)) {
+                stack.extend(successors.get(&page).into_iter().flatten());
+            }
+        }
+    }
+    closure
+}
+
+/// Rules that follow from the others: the right page is still reachable from the left one without
+/// the rule itself. On cyclic rules the closure alone would count rules that only imply themselves.
+fn redundant_rules(rules: &Rules) -> Vec<Rule> {
+    let successors: HashMap<i32, Vec<i32>> = rules.iter().copied().into_group_map();
+    rules
+        .iter()
+        .filter(|&&rule| reachable_without(&successors, rule))
+        .copied()
+        .sorted()
+        .collect()
+}
+
+fn reachable_without(successors: &HashMap<i32, Vec<i32>>, (l, r): Rule) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = vec![l];
+    while let Some(page) = stack.pop() {
+        for &next in successors.get(&page).into_iter().flatten() {
+            if (page, next) == (l, r) {
+                continue;
+            }
+            if next == r {
+                return true;
+            }
+            if seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    false
+}
+
+/// Whether the rules leave a single way to order the pages of the update. That is when the
+/// topological order is a chain of rules.
+fn is_total_order(update: &Update, rules: &Rules) -> bool {
+    reorder(update, rules).is_ok_and(|fixed| {
+        fixed
+            .iter()
+            .tuple_windows()
+            .all(|(&l, &r)| rules.contains(&(l, r)))
+    })
+}
+
+/// The order of all the pages, if the rules define one.
+fn global_order(rules: &Rules) -> Option<Update> {
+    let pages = pages(rules);
+    is_total_order(&pages, rules).then(|| reorder(&pages, rules).unwrap())
+}
+
+pub(crate) fn diagnostics(input: &str) -> String {
+    let input = parse_input(input);
+    let mut lines = vec![
+        format!(
+            "{} rules over {} pages",
+            input.rules.len(),
+            pages(&input.rules).len()
+        ),
+        format!(
+            "{} redundant rules, {} orderings implied",
+            redundant_rules(&input.rules).len(),
+            closure(&input.rules).len()
+        ),
+    ];
+    match global_order(&input.rules) {
+        Some(order) => lines.push(format!("Global order: {}", order.iter().join(","))),
+        None => lines.push("No global order".to_string()),
+    }
+    let partial = input
         .updates
         .iter()
-        .filter(|up| update_is_correct(up, &input.rules));
+        .filter(|up| !is_total_order(up, &input.rules))
+        .count();
+    lines.push(format!(
+        "{} of {} updates are not totally ordered",
+        partial,
+        input.updates.len()
+    ));
+    lines.join("\n")
+}
+
+#[aoc(day5, part1)]
+fn part1(input: &Input) -> i32 {
+    let ranking = Ranking::new(&input.rules);
+    let correct = input.updates.iter().filter(|up| match &ranking {
+        Some(ranking) => ranking.update_is_correct(up),
+        None => update_is_correct(up, &input.rules),
+    });
 
     correct
         .map(|up| {
@@ -57,35 +189,78 @@ fn part1(input: &Input) -> i32 {
         .sum()
 }
 
-fn fix(update: &Update, rules: &HashSet<Rule>) -> Update {
-    let mut fixed = update.clone();
-    'outer: loop {
-        for i in 0..fixed.len() - 1 {
-            let (l, r) = (fixed[i], fixed[i + 1]);
-            if rules.contains(&(r, l)) {
-                fixed.swap(i, i + 1);
-                continue 'outer;
+/// Pages whose rules contradict each other, each one must come before the next.
+#[derive(Debug, PartialEq)]
+struct Cycle(Vec<i32>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pages = self.0.iter().chain(self.0.first()).join(" -> ");
+        write!(f, "Rules form a cycle: {}", pages)
+    }
+}
+
+impl Error for Cycle {}
+
+/// Topological sort of the rules between the pages of the update. Of the pages free to go next,
+/// the earliest in the update goes first, so pages only move as far as the rules make them.
+fn reorder(update: &Update, rules: &Rules) -> Result<Update, Cycle> {
+    let n = update.len();
+    let mut successors = vec![vec![]; n];
+    let mut predecessors = vec![vec![]; n];
+    for i in 0..n {
+        for j in 0..n {
+            if rules.contains(&(update[i], update[j])) {
+                successors[i].push(j);
+                predecessors[j].push(i);
+            }
+        }
+    }
+
+    let mut in_degree = predecessors.iter().map(|p| p.len()).collect_vec();
+    let mut ready: BinaryHeap<Reverse<usize>> =
+        (0..n).filter(|&i| in_degree[i] == 0).map(Reverse).collect();
+    let mut fixed = Vec::with_capacity(n);
+    while let Some(Reverse(i)) = ready.pop() {
+        fixed.push(update[i]);
+        for &j in &successors[i] {
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push(Reverse(j));
             }
         }
-        break;
     }
 
-    fixed
+    if fixed.len() == n {
+        return Ok(fixed);
+    }
+
+    // Every page left has a predecessor left, walking back from any of them ends in a cycle
+    let mut seen = vec![None; n];
+    let mut path = vec![];
+    let mut i = (0..n).find(|&i| in_degree[i] > 0).unwrap();
+    while seen[i].is_none() {
+        seen[i] = Some(path.len());
+        path.push(i);
+        i = *predecessors[i].iter().find(|&&p| in_degree[p] > 0).unwrap();
+    }
+    let mut cycle = path[seen[i].unwrap()..]
+        .iter()
+        .map(|&i| update[i])
+        .collect_vec();
+    cycle.reverse();
+    Err(Cycle(cycle))
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &Input) -> i32 {
-    let incorrect = input
+fn part2(input: &Input) -> Result<i32, Cycle> {
+    input
         .updates
         .iter()
-        .filter(|up| !update_is_correct(up, &input.rules));
-
-    let fixed = incorrect.map(|up| fix(up, &input.rules));
-
-    fixed
+        .filter(|up| !update_is_correct(up, &input.rules))
         .map(|up| {
-            let middle = up.len() / 2;
-            up[middle]
+            let fixed = reorder(up, &input.rules)?;
+            Ok(fixed[fixed.len() / 2])
         })
         .sum()
 }
@@ -131,6 +306,110 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&parse_input(TEST_INPUT)), 123);
+        assert_eq!(part2(&parse_input(TEST_INPUT)), Ok(123));
+    }
+
+    #[test]
+    fn test_reorder() {
+        let input = parse_input(TEST_INPUT);
+        assert_eq!(
+            reorder(&vec![97, 13, 75, 29, 47], &input.rules),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+        // 4 only moves past 2, unrelated pages stay where they are
+        assert_eq!(
+            reorder(&vec![5, 4, 3, 2, 1], &HashSet::from([(2, 4)])),
+            Ok(vec![5, 3, 2, 4, 1])
+        );
+        // 3 waits for 2, and 1 is free before 2 is
+        assert_eq!(
+            reorder(&vec![3, 1, 2], &HashSet::from([(2, 3)])),
+            Ok(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_reorder_cycle() {
+        let rules = HashSet::from([(1, 2), (2, 3), (3, 1), (3, 4), (5, 1)]);
+        let cycle = reorder(&vec![4, 3, 2, 5, 1], &rules).unwrap_err();
+        assert_eq!(cycle, Cycle(vec![1, 2, 3]));
+        assert_eq!(cycle.to_string(), "Rules form a cycle: 1 -> 2 -> 3 -> 1");
+
+        let rules = HashSet::from([(7, 7)]);
+        assert_eq!(reorder(&vec![7], &rules), Err(Cycle(vec![7])));
+    }
+
+    #[test]
+    fn test_closure() {
+        let rules = HashSet::from([(1, 2), (2, 3), (1, 3), (3, 4)]);
+        assert_eq!(
+            closure(&rules).into_iter().sorted().collect_vec(),
+            vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)]
+        );
+        assert_eq!(redundant_rules(&rules), vec![(1, 3)]);
+
+        let input = parse_input(TEST_INPUT);
+        assert_eq!(closure(&input.rules), input.rules);
+        assert_eq!(redundant_rules(&input.rules).len(), 21 - 6);
+    }
+
+    #[test]
+    fn test_redundant_rules_cyclic() {
+        // The closure has (3, 2) through the cycle, but only (1, 2) itself puts 1 before 2
+        let rules = HashSet::from([(1, 2), (1, 3), (3, 1)]);
+        assert!(redundant_rules(&rules).is_empty());
+
+        let rules = HashSet::from([(1, 2), (2, 3), (1, 3), (3, 1)]);
+        assert_eq!(redundant_rules(&rules), vec![(1, 3)]);
+        let rules = HashSet::from([(1, 2), (2, 1)]);
+        assert!(redundant_rules(&rules).is_empty());
+    }
+
+    #[test]
+    fn test_total_order() {
+        let rules = HashSet::from([(1, 2), (2, 3), (1, 4)]);
+        assert!(is_total_order(&vec![3, 2, 1], &rules));
+        assert!(!is_total_order(&vec![3, 2, 1, 4], &rules));
+        assert_eq!(global_order(&rules), None);
+        assert_eq!(
+            global_order(&HashSet::from([(2, 3), (1, 2)])),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(global_order(&HashSet::from([(1, 2), (2, 1)])), None);
+
+        let input = parse_input(TEST_INPUT);
+        assert_eq!(
+            global_order(&input.rules),
+            Some(vec![97, 75, 47, 61, 53, 29, 13])
+        );
+        assert!(input
+            .updates
+            .iter()
+            .all(|up| is_total_order(up, &input.rules)));
+    }
+
+    #[test]
+    fn test_ranking() {
+        let input = parse_input(TEST_INPUT);
+        let ranking = Ranking::new(&input.rules).unwrap();
+        for update in &input.updates {
+            assert_eq!(
+                ranking.update_is_correct(update),
+                update_is_correct(update, &input.rules)
+            );
+        }
+        // 1 before 3 only through 2, which is not in the update
+        assert!(Ranking::new(&HashSet::from([(1, 2), (2, 3)])).is_none());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert_eq!(
+            diagnostics(TEST_INPUT),
+            "21 rules over 7 pages
+15 redundant rules, 21 orderings implied
+Global order: 97,75,47,61,53,29,13
+0 of 6 updates are not totally ordered"
+        );
     }
 }
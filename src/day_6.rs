@@ -1,13 +1,18 @@
+use crate::report::Render;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::HashSet;
+use std::fmt;
+use std::thread;
 
 type Coord = (i32, i32);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Input {
     obstacles: HashSet<Coord>,
     starting_point: Coord,
-    side: i32,
+    starting_direction: Direction,
+    height: i32,
+    width: i32,
 }
 
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
@@ -27,6 +32,36 @@ fn step(p: &Coord, dir: &Direction) -> Coord {
     }
 }
 
+impl Direction {
+    fn from_marker(c: char) -> Option<Direction> {
+        match c {
+            '^' => Some(Direction::Up),
+            '>' => Some(Direction::Right),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    fn marker(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
+}
+
 fn turn(dir: &Direction) -> Direction {
     match dir {
         Direction::Up => Direction::Right,
@@ -36,41 +71,93 @@ fn turn(dir: &Direction) -> Direction {
     }
 }
 
-fn in_bounds(p: &Coord, side: i32) -> bool {
-    (0..side).contains(&p.0) && (0..side).contains(&p.1)
+fn in_bounds(p: &Coord, height: i32, width: i32) -> bool {
+    (0..height).contains(&p.0) && (0..width).contains(&p.1)
 }
 
-#[aoc_generator(day6)]
-fn parse_input(input: &str) -> Input {
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    NoGuard,
+    MultipleGuards(Vec<Coord>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoGuard => write!(f, "no guard on the map"),
+            ParseError::MultipleGuards(guards) => {
+                write!(f, "{} guards on the map, at {:?}", guards.len(), guards)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Guard = (Coord, Direction);
+
+/// A map with any number of guards, in reading order.
+#[derive(Debug)]
+struct Patrol {
+    obstacles: HashSet<Coord>,
+    guards: Vec<Guard>,
+    height: i32,
+    width: i32,
+}
+
+fn parse_patrol(input: &str) -> Patrol {
     let mut obstacles = HashSet::new();
-    let mut starting_point: Coord = (0, 0);
+    let mut guards: Vec<Guard> = vec![];
 
     for (y, line) in input.lines().enumerate() {
         for (x, ch) in line.chars().enumerate() {
-            match ch {
-                '#' => {
-                    let _ = obstacles.insert((y as i32, x as i32));
-                }
-                '^' => starting_point = (y as i32, x as i32),
-                _ => {}
+            let p = (y as i32, x as i32);
+            if ch == '#' {
+                obstacles.insert(p);
+            } else if let Some(dir) = Direction::from_marker(ch) {
+                guards.push((p, dir));
             }
         }
     }
 
-    Input {
+    Patrol {
         obstacles,
-        starting_point,
-        side: input.lines().count() as i32,
+        guards,
+        height: input.lines().count() as i32,
+        width: input.lines().map(|l| l.chars().count()).max().unwrap_or(0) as i32,
     }
 }
 
+#[aoc_generator(day6)]
+fn parse_input(input: &str) -> Result<Input, ParseError> {
+    let patrol = parse_patrol(input);
+
+    let (starting_point, starting_direction) = match patrol.guards[..] {
+        [] => return Err(ParseError::NoGuard),
+        [guard] => guard,
+        _ => {
+            return Err(ParseError::MultipleGuards(
+                patrol.guards.into_iter().map(|(p, _)| p).collect(),
+            ))
+        }
+    };
+
+    Ok(Input {
+        obstacles: patrol.obstacles,
+        starting_point,
+        starting_direction,
+        height: patrol.height,
+        width: patrol.width,
+    })
+}
+
 #[allow(dead_code)]
 fn print_visited(input: &Input, visited: &HashSet<Coord>) {
-    for y in 0..input.side {
-        for x in 0..input.side {
+    for y in 0..input.height {
+        for x in 0..input.width {
             let p = (y, x);
             let c = match p {
-                p if p == input.starting_point => '^',
+                p if p == input.starting_point => input.starting_direction.marker(),
                 p if input.obstacles.contains(&p) => '#',
                 p if visited.contains(&p) => 'X',
                 _ => '.',
@@ -83,7 +170,7 @@ fn print_visited(input: &Input, visited: &HashSet<Coord>) {
 
 fn get_path(input: &Input) -> HashSet<Coord> {
     let mut guard_at = input.starting_point;
-    let mut guard_direction = Direction::Up;
+    let mut guard_direction = input.starting_direction;
 
     let mut visited: HashSet<Coord> = HashSet::new();
 
@@ -91,7 +178,7 @@ fn get_path(input: &Input) -> HashSet<Coord> {
         visited.insert(guard_at);
 
         let next = step(&guard_at, &guard_direction);
-        if !in_bounds(&next, input.side) {
+        if !in_bounds(&next, input.height, input.width) {
             // guard stepped out
             return visited;
         }
@@ -113,9 +200,39 @@ fn part1(input: &Input) -> usize {
     visited.len()
 }
 
-fn does_loop(starting_point: &Coord, obstacles: &HashSet<Coord>, side: i32) -> bool {
+pub(crate) fn render(input: &str) -> Option<Render> {
+    let input = parse_input(input).ok()?;
+    let visited = get_path(&input);
+
+    let mut render = Render::new(
+        "Day 6 guard path",
+        input.width as usize,
+        input.height as usize,
+    );
+    for y in 0..input.height {
+        for x in 0..input.width {
+            let p = (y, x);
+            let class = match p {
+                p if p == input.starting_point => 3,
+                p if input.obstacles.contains(&p) => 1,
+                p if visited.contains(&p) => 2,
+                _ => 0,
+            };
+            render.set(x as usize, y as usize, class);
+        }
+    }
+    Some(render)
+}
+
+fn does_loop(
+    starting_point: &Coord,
+    starting_direction: &Direction,
+    obstacles: &HashSet<Coord>,
+    height: i32,
+    width: i32,
+) -> bool {
     let mut guard_at = *starting_point;
-    let mut guard_direction = Direction::Up;
+    let mut guard_direction = *starting_direction;
 
     let mut visited: HashSet<(Coord, Direction)> = HashSet::new();
 
@@ -127,7 +244,7 @@ fn does_loop(starting_point: &Coord, obstacles: &HashSet<Coord>, side: i32) -> b
         }
 
         let next = step(&guard_at, &guard_direction);
-        if !in_bounds(&next, side) {
+        if !in_bounds(&next, height, width) {
             // guard stepped out without entering a loop
             return false;
         }
@@ -147,8 +264,8 @@ fn does_loop(starting_point: &Coord, obstacles: &HashSet<Coord>, side: i32) -> b
 fn part2(input: &Input) -> i32 {
     let mut loop_causing_obstacles: HashSet<Coord> = HashSet::new();
 
-    for y in 0..input.side {
-        for x in 0..input.side {
+    for y in 0..input.height {
+        for x in 0..input.width {
             let new_obstacle = (y, x);
 
             if input.obstacles.contains(&new_obstacle) || new_obstacle == input.starting_point {
@@ -162,7 +279,13 @@ fn part2(input: &Input) -> i32 {
                 set
             };
 
-            if does_loop(&input.starting_point, &mutated_obstacles, input.side) {
+            if does_loop(
+                &input.starting_point,
+                &input.starting_direction,
+                &mutated_obstacles,
+                input.height,
+                input.width,
+            ) {
                 loop_causing_obstacles.insert(new_obstacle);
             }
         }
@@ -191,13 +314,501 @@ fn part2_faster(input: &Input) -> i32 {
             set
         };
 
-        if does_loop(&input.starting_point, &mutated_obstacles, input.side) {
+        if does_loop(
+            &input.starting_point,
+            &input.starting_direction,
+            &mutated_obstacles,
+            input.height,
+            input.width,
+        ) {
             loop_causing_obstacles.insert(new_obstacle);
         }
     }
     loop_causing_obstacles.len() as i32
 }
 
+/// For every cell and direction, the coordinate along that direction of the next obstacle, so the
+/// guard can go from turn to turn. Off the map is -1 or the length of the row or column.
+struct JumpTable {
+    height: i32,
+    width: i32,
+    next: [Vec<i32>; 4], // by Direction::index, then y * width + x
+}
+
+/// Guard states already seen during a simulation, kept between simulations to save allocations.
+struct Scratch {
+    seen: Vec<u8>, // bit per Direction::index
+    touched: Vec<usize>,
+}
+
+impl Scratch {
+    fn new(table: &JumpTable) -> Self {
+        Scratch {
+            seen: vec![0; (table.height * table.width) as usize],
+            touched: vec![],
+        }
+    }
+
+    // Whether the state was new
+    fn insert(&mut self, i: usize, dir: &Direction) -> bool {
+        let bit = 1 << dir.index();
+        if self.seen[i] & bit != 0 {
+            return false;
+        }
+        if self.seen[i] == 0 {
+            self.touched.push(i);
+        }
+        self.seen[i] |= bit;
+        true
+    }
+
+    fn clear(&mut self) {
+        for i in self.touched.drain(..) {
+            self.seen[i] = 0;
+        }
+    }
+}
+
+impl JumpTable {
+    fn new(obstacles: &HashSet<Coord>, height: i32, width: i32) -> Self {
+        let cells = (height * width) as usize;
+        let mut next = [
+            vec![0; cells],
+            vec![0; cells],
+            vec![0; cells],
+            vec![0; cells],
+        ];
+        let index = |y: i32, x: i32| (y * width + x) as usize;
+
+        for x in 0..width {
+            let mut last = -1;
+            for y in 0..height {
+                next[Direction::Up.index()][index(y, x)] = last;
+                if obstacles.contains(&(y, x)) {
+                    last = y;
+                }
+            }
+            let mut last = height;
+            for y in (0..height).rev() {
+                next[Direction::Down.index()][index(y, x)] = last;
+                if obstacles.contains(&(y, x)) {
+                    last = y;
+                }
+            }
+        }
+        for y in 0..height {
+            let mut last = -1;
+            for x in 0..width {
+                next[Direction::Left.index()][index(y, x)] = last;
+                if obstacles.contains(&(y, x)) {
+                    last = x;
+                }
+            }
+            let mut last = width;
+            for x in (0..width).rev() {
+                next[Direction::Right.index()][index(y, x)] = last;
+                if obstacles.contains(&(y, x)) {
+                    last = x;
+                }
+            }
+        }
+
+        JumpTable {
+            height,
+            width,
+            next,
+        }
+    }
+
+    fn index(&self, p: &Coord) -> usize {
+        (p.0 * self.width + p.1) as usize
+    }
+
+    /// Where the guard stops in front of the next obstacle, the extra one included. None if the
+    /// guard leaves the map.
+    fn jump(&self, p: &Coord, dir: &Direction, extra: &Coord) -> Option<Coord> {
+        let next = self.next[dir.index()][self.index(p)];
+        match dir {
+            Direction::Up => {
+                let y = if extra.1 == p.1 && (next + 1..p.0).contains(&extra.0) {
+                    extra.0
+                } else {
+                    next
+                };
+                (y >= 0).then_some((y + 1, p.1))
+            }
+            Direction::Down => {
+                let y = if extra.1 == p.1 && (p.0 + 1..next).contains(&extra.0) {
+                    extra.0
+                } else {
+                    next
+                };
+                (y < self.height).then_some((y - 1, p.1))
+            }
+            Direction::Left => {
+                let x = if extra.0 == p.0 && (next + 1..p.1).contains(&extra.1) {
+                    extra.1
+                } else {
+                    next
+                };
+                (x >= 0).then_some((p.0, x + 1))
+            }
+            Direction::Right => {
+                let x = if extra.0 == p.0 && (p.1 + 1..next).contains(&extra.1) {
+                    extra.1
+                } else {
+                    next
+                };
+                (x < self.width).then_some((p.0, x - 1))
+            }
+        }
+    }
+
+    /// Same as `does_loop` with the extra obstacle added, from any point of the patrol.
+    fn does_loop(
+        &self,
+        from: &Coord,
+        dir: &Direction,
+        extra: &Coord,
+        scratch: &mut Scratch,
+    ) -> bool {
+        scratch.clear();
+        let mut guard_at = *from;
+        let mut guard_direction = *dir;
+        loop {
+            match self.jump(&guard_at, &guard_direction, extra) {
+                None => return false,
+                Some(stop) => {
+                    guard_at = stop;
+                    guard_direction = turn(&guard_direction);
+                    if !scratch.insert(self.index(&guard_at), &guard_direction) {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A place for an extra obstacle, with the guard state just before reaching it for the first
+/// time: the patrol is the same up to there.
+struct Candidate {
+    obstacle: Coord,
+    guard_at: Coord,
+    direction: Direction,
+}
+
+fn candidates(input: &Input) -> Vec<Candidate> {
+    let mut candidates = vec![];
+    let mut visited: HashSet<Coord> = HashSet::from([input.starting_point]);
+    let mut guard_at = input.starting_point;
+    let mut guard_direction = input.starting_direction;
+
+    loop {
+        let next = step(&guard_at, &guard_direction);
+        if !in_bounds(&next, input.height, input.width) {
+            return candidates;
+        }
+        if input.obstacles.contains(&next) {
+            guard_direction = turn(&guard_direction);
+            continue;
+        }
+        if visited.insert(next) {
+            candidates.push(Candidate {
+                obstacle: next,
+                guard_at,
+                direction: guard_direction,
+            });
+        }
+        guard_at = next;
+    }
+}
+
+#[aoc(day6, part2, jump)]
+fn part2_jump(input: &Input) -> usize {
+    let table = JumpTable::new(&input.obstacles, input.height, input.width);
+    let mut scratch = Scratch::new(&table);
+    candidates(input)
+        .iter()
+        .filter(|c| table.does_loop(&c.guard_at, &c.direction, &c.obstacle, &mut scratch))
+        .count()
+}
+
+/// Loop-causing obstacles in the order of the original path. The candidates are split in
+/// contiguous chunks, one per thread, so the order doesn't depend on the scheduling.
+fn loop_obstacles_parallel(input: &Input, threads: usize) -> Vec<Coord> {
+    let table = JumpTable::new(&input.obstacles, input.height, input.width);
+    let candidates = candidates(input);
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let handles = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let table = &table;
+                scope.spawn(move || {
+                    let mut scratch = Scratch::new(table);
+                    chunk
+                        .iter()
+                        .filter(|c| {
+                            table.does_loop(&c.guard_at, &c.direction, &c.obstacle, &mut scratch)
+                        })
+                        .map(|c| c.obstacle)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[aoc(day6, part2, parallel)]
+fn part2_parallel(input: &Input) -> usize {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    loop_obstacles_parallel(input, threads).len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fate {
+    Exited { tick: usize },
+    Collided { tick: usize, at: Coord },
+    Looping,
+}
+
+#[derive(Debug)]
+struct Lockstep {
+    fates: Vec<Fate>, // by guard
+    visited: HashSet<Coord>,
+}
+
+/// Moves all the guards at once, one step or turn per tick, with the rules of `get_path`. Guards
+/// that end up on the same cell or walk through each other collide and stop there.
+fn lockstep(patrol: &Patrol) -> Lockstep {
+    let mut guards = patrol.guards.clone();
+    let mut fates: Vec<Option<Fate>> = vec![None; guards.len()];
+    let mut visited: HashSet<Coord> = guards.iter().map(|(p, _)| *p).collect();
+
+    // Brent's cycle detection over the guards still walking, without storing their states
+    let walking = |fates: &[Option<Fate>], guards: &[Guard]| {
+        (0..guards.len())
+            .filter(|&i| fates[i].is_none())
+            .map(|i| (i, guards[i]))
+            .collect::<Vec<_>>()
+    };
+    let mut checkpoint = walking(&fates, &guards);
+    let (mut power, mut length) = (1, 0);
+
+    for tick in 1.. {
+        let current = walking(&fates, &guards);
+        if current.is_empty() {
+            break;
+        }
+
+        let before = guards.clone();
+        for &(i, (at, dir)) in &current {
+            let next = step(&at, &dir);
+            if !in_bounds(&next, patrol.height, patrol.width) {
+                fates[i] = Some(Fate::Exited { tick });
+            } else if patrol.obstacles.contains(&next) {
+                guards[i].1 = turn(&dir);
+            } else {
+                guards[i].0 = next;
+                visited.insert(next);
+            }
+        }
+
+        let on_map = (0..guards.len())
+            .filter(|&i| fates[i].is_none())
+            .collect::<Vec<_>>();
+        let mut collided = vec![];
+        for (n, &i) in on_map.iter().enumerate() {
+            for &j in &on_map[n + 1..] {
+                let same_cell = guards[i].0 == guards[j].0;
+                let swapped = guards[i].0 == before[j].0 && guards[j].0 == before[i].0;
+                if same_cell || swapped {
+                    collided.extend([i, j]);
+                }
+            }
+        }
+        for &i in &collided {
+            let at = guards[i].0;
+            fates[i] = Some(Fate::Collided { tick, at });
+        }
+
+        let state = walking(&fates, &guards);
+        if state.len() != current.len() {
+            // Another set of guards, start over
+            checkpoint = state;
+            (power, length) = (1, 0);
+            continue;
+        }
+        if state == checkpoint {
+            break;
+        }
+        length += 1;
+        if length == power {
+            checkpoint = state;
+            (power, length) = (power * 2, 0);
+        }
+    }
+
+    Lockstep {
+        fates: fates
+            .into_iter()
+            .map(|fate| fate.unwrap_or(Fate::Looping))
+            .collect(),
+        visited,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Exits { visited: usize },
+    Loops,
+}
+
+/// Obstacles the guard runs into, in order. Removing any other obstacle changes nothing.
+fn hit_obstacles(input: &Input) -> Vec<Coord> {
+    let mut hits = vec![];
+    let mut seen: HashSet<Guard> = HashSet::new();
+    let mut guard_at = input.starting_point;
+    let mut guard_direction = input.starting_direction;
+
+    while seen.insert((guard_at, guard_direction)) {
+        let next = step(&guard_at, &guard_direction);
+        if !in_bounds(&next, input.height, input.width) {
+            break;
+        }
+        if input.obstacles.contains(&next) {
+            if !hits.contains(&next) {
+                hits.push(next);
+            }
+            guard_direction = turn(&guard_direction);
+        } else {
+            guard_at = next;
+        }
+    }
+    hits
+}
+
+fn outcome(input: &Input) -> Outcome {
+    if does_loop(
+        &input.starting_point,
+        &input.starting_direction,
+        &input.obstacles,
+        input.height,
+        input.width,
+    ) {
+        Outcome::Loops
+    } else {
+        Outcome::Exits {
+            visited: get_path(input).len(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct WhatIf {
+    baseline: Outcome,
+    removals: Vec<(Coord, Outcome)>, // of the obstacles the guard hits
+    most_visited: Vec<Coord>,
+    fewest_visited: Vec<Coord>,
+    loop_breakers: Vec<Coord>, // when the guard loops without the removal
+}
+
+/// What happens when each single obstacle is removed, and the best ones to remove.
+fn what_if_removed(input: &Input) -> WhatIf {
+    let baseline = outcome(input);
+    let removals = hit_obstacles(input)
+        .into_iter()
+        .map(|obstacle| {
+            let mut removed = input.clone();
+            removed.obstacles.remove(&obstacle);
+            (obstacle, outcome(&removed))
+        })
+        .collect::<Vec<_>>();
+
+    let visited = |outcome: &Outcome| match outcome {
+        Outcome::Exits { visited } => Some(*visited),
+        Outcome::Loops => None,
+    };
+    let best = |target: Option<usize>| {
+        removals
+            .iter()
+            .filter(|(_, o)| target.is_some() && visited(o) == target)
+            .map(|(p, _)| *p)
+            .collect::<Vec<_>>()
+    };
+    let most_visited = best(removals.iter().filter_map(|(_, o)| visited(o)).max());
+    let fewest_visited = best(removals.iter().filter_map(|(_, o)| visited(o)).min());
+    let loop_breakers = match baseline {
+        Outcome::Loops => removals
+            .iter()
+            .filter(|(_, o)| *o != Outcome::Loops)
+            .map(|(p, _)| *p)
+            .collect(),
+        Outcome::Exits { .. } => vec![],
+    };
+
+    WhatIf {
+        baseline,
+        removals,
+        most_visited,
+        fewest_visited,
+        loop_breakers,
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Exits { visited } => write!(f, "exits after visiting {} cells", visited),
+            Outcome::Loops => write!(f, "loops"),
+        }
+    }
+}
+
+/// What-if removals for a single guard, the fate of every guard when there are more.
+pub(crate) fn diagnostics(input: &str) -> String {
+    let patrol = parse_patrol(input);
+    let mut lines = vec![];
+
+    if patrol.guards.len() > 1 {
+        let lockstep = lockstep(&patrol);
+        for (&(p, dir), fate) in patrol.guards.iter().zip(&lockstep.fates) {
+            let fate = match fate {
+                Fate::Exited { tick } => format!("exits after {} ticks", tick),
+                Fate::Collided { tick, at } => format!("collides at {:?} after {} ticks", at, tick),
+                Fate::Looping => "loops".to_string(),
+            };
+            lines.push(format!("Guard {} at {:?}: {}", dir.marker(), p, fate));
+        }
+        lines.push(format!("{} cells visited", lockstep.visited.len()));
+        return lines.join("\n");
+    }
+
+    let input = match parse_input(input) {
+        Ok(input) => input,
+        Err(e) => return e.to_string(),
+    };
+    let what_if = what_if_removed(&input);
+    let best = |name: &str, obstacles: &[Coord]| {
+        let first = obstacles.first()?;
+        let (_, outcome) = what_if.removals.iter().find(|(p, _)| p == first)?;
+        Some(format!("{}: removing {:?} {}", name, obstacles, outcome))
+    };
+    lines.push(format!("As is, the guard {}", what_if.baseline));
+    lines.extend(best("Most visited", &what_if.most_visited));
+    lines.extend(best("Fewest visited", &what_if.fewest_visited));
+    if what_if.baseline == Outcome::Loops {
+        lines.push(format!("Loop breakers: {:?}", what_if.loop_breakers));
+    }
+    lines.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,16 +827,295 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&parse_input(TEST_INPUT)), 41);
+        assert_eq!(part1(&parse_input(TEST_INPUT).unwrap()), 41);
+    }
+
+    #[test]
+    fn test_render() {
+        let render = render(TEST_INPUT).unwrap();
+        assert_eq!((render.width, render.height), (10, 10));
+        assert_eq!(render.cells.iter().filter(|c| **c == 1).count(), 8);
+        assert_eq!(render.cells.iter().filter(|c| **c >= 2).count(), 41);
+        assert_eq!(render.get(4, 6), 3);
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&parse_input(TEST_INPUT)), 6);
+        assert_eq!(part2(&parse_input(TEST_INPUT).unwrap()), 6);
     }
 
     #[test]
     fn test_part2_faster() {
-        assert_eq!(part2_faster(&parse_input(TEST_INPUT)), 6);
+        assert_eq!(part2_faster(&parse_input(TEST_INPUT).unwrap()), 6);
+    }
+
+    #[test]
+    fn test_part2_jump() {
+        assert_eq!(part2_jump(&parse_input(TEST_INPUT).unwrap()), 6);
+    }
+
+    #[test]
+    fn test_jump() {
+        let input = parse_input(TEST_INPUT).unwrap();
+        let table = JumpTable::new(&input.obstacles, input.height, input.width);
+        let none = (-1, -1);
+        assert_eq!(table.jump(&(6, 4), &Direction::Up, &none), Some((1, 4)));
+        assert_eq!(table.jump(&(1, 4), &Direction::Right, &none), Some((1, 8)));
+        assert_eq!(table.jump(&(7, 7), &Direction::Down, &none), None);
+        assert_eq!(table.jump(&(8, 5), &Direction::Left, &none), Some((8, 1)));
+        // The extra obstacle only counts when it comes first
+        assert_eq!(table.jump(&(6, 4), &Direction::Up, &(3, 4)), Some((4, 4)));
+        assert_eq!(table.jump(&(6, 4), &Direction::Up, &(7, 4)), Some((1, 4)));
+        assert_eq!(table.jump(&(6, 4), &Direction::Up, &(0, 4)), Some((1, 4)));
+        assert_eq!(table.jump(&(7, 7), &Direction::Down, &(9, 7)), Some((8, 7)));
+    }
+
+    #[test]
+    fn test_jump_matches_does_loop() {
+        let input = parse_input(TEST_INPUT).unwrap();
+        let table = JumpTable::new(&input.obstacles, input.height, input.width);
+        let mut scratch = Scratch::new(&table);
+        let candidates = candidates(&input);
+        assert_eq!(candidates.len(), 40);
+        for c in candidates {
+            let mut obstacles = input.obstacles.clone();
+            obstacles.insert(c.obstacle);
+            assert_eq!(
+                table.does_loop(&c.guard_at, &c.direction, &c.obstacle, &mut scratch),
+                does_loop(
+                    &input.starting_point,
+                    &input.starting_direction,
+                    &obstacles,
+                    input.height,
+                    input.width
+                ),
+                "{:?}",
+                c.obstacle
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_input("..#\n...").unwrap_err(), ParseError::NoGuard);
+        let error = parse_input(".^.\n..<").unwrap_err();
+        assert_eq!(error, ParseError::MultipleGuards(vec![(0, 1), (1, 2)]));
+        assert_eq!(
+            error.to_string(),
+            "2 guards on the map, at [(0, 1), (1, 2)]"
+        );
+    }
+
+    #[test]
+    fn test_rectangular_map() {
+        // 3 rows of 8
+        let input = parse_input("#.......\n>......#\n........").unwrap();
+        assert_eq!((input.height, input.width), (3, 8));
+        assert_eq!(input.starting_direction, Direction::Right);
+        // Right to the wall, then down out of the map
+        assert_eq!(part1(&input), 7 + 1);
+        assert_eq!(render(">......#\n........").unwrap().width, 8);
+
+        let input = parse_input("..#\n...\n...\n...\n.v.\n...").unwrap();
+        assert_eq!((input.height, input.width), (6, 3));
+        assert_eq!(part1(&input), 2);
+        assert_eq!(part2_faster(&input), part2(&input));
+        assert_eq!(part2_jump(&input), part2(&input) as usize);
+    }
+
+    #[test]
+    fn test_directions_match_slow_part2() {
+        // The example turned so the guard starts in each direction
+        let rows = TEST_INPUT.lines().collect::<Vec<_>>();
+        let columns = (0..rows[0].len())
+            .map(|x| {
+                rows.iter()
+                    .rev()
+                    .map(|r| r.as_bytes()[x] as char)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        // Rotated clockwise, the guard faces right
+        let rotated = columns.join("\n").replace('^', ">");
+        let input = parse_input(&rotated).unwrap();
+        assert_eq!(input.starting_direction, Direction::Right);
+        assert_eq!(part1(&input), 41);
+        assert_eq!(part2(&input), 6);
+        assert_eq!(part2_jump(&input), 6);
+
+        let wide = TEST_INPUT.replace('\n', "..\n").replace('^', "<");
+        let input = parse_input(&wide).unwrap();
+        assert_eq!((input.height, input.width), (10, 12));
+        assert_eq!(part2_faster(&input), part2(&input));
+        assert_eq!(part2_jump(&input), part2(&input) as usize);
+    }
+
+    #[test]
+    fn test_lockstep() {
+        let collide = lockstep(&parse_patrol("v..\n...\n^.."));
+        assert_eq!(
+            collide.fates,
+            vec![
+                Fate::Collided {
+                    tick: 1,
+                    at: (1, 0)
+                };
+                2
+            ]
+        );
+
+        // Walking through each other
+        let swap = lockstep(&parse_patrol(">.\n<."));
+        assert_eq!(
+            swap.fates,
+            vec![Fate::Exited { tick: 2 }, Fate::Exited { tick: 1 }]
+        );
+        let swap = lockstep(&parse_patrol("><"));
+        assert_eq!(
+            swap.fates,
+            vec![
+                Fate::Collided {
+                    tick: 1,
+                    at: (0, 1)
+                },
+                Fate::Collided {
+                    tick: 1,
+                    at: (0, 0)
+                },
+            ]
+        );
+
+        let looping = lockstep(&parse_patrol(LOOP_INPUT));
+        assert_eq!(looping.fates, vec![Fate::Looping, Fate::Exited { tick: 1 }]);
+        assert_eq!(looping.visited.len(), 5);
+
+        // A single guard agrees with get_path
+        let single = lockstep(&parse_patrol(TEST_INPUT));
+        assert_eq!(single.visited.len(), 41);
+        assert!(matches!(single.fates[..], [Fate::Exited { .. }]));
+    }
+
+    #[test]
+    fn test_lockstep_late_collision() {
+        // The first guard is already going round its loop when the second walks into it
+        let map = LOOP_INPUT.replace('>', ".") + &"....\n".repeat(12) + ".^..\n";
+        let fates = lockstep(&parse_patrol(&map)).fates;
+        assert_eq!(
+            fates,
+            vec![
+                Fate::Collided {
+                    tick: 14,
+                    at: (2, 1)
+                };
+                2
+            ]
+        );
+    }
+
+    static LOOP_INPUT: &str = ".#..
+.^.#
+#...
+..#>
+";
+
+    #[test]
+    fn test_what_if_removed() {
+        let input = parse_input(&LOOP_INPUT.replace('>', ".")).unwrap();
+        let what_if = what_if_removed(&input);
+        assert_eq!(what_if.baseline, Outcome::Loops);
+        assert_eq!(
+            what_if.removals,
+            vec![
+                ((0, 1), Outcome::Exits { visited: 2 }),
+                ((1, 3), Outcome::Exits { visited: 3 }),
+                ((3, 2), Outcome::Exits { visited: 4 }),
+                ((2, 0), Outcome::Exits { visited: 5 }),
+            ]
+        );
+        assert_eq!(what_if.most_visited, vec![(2, 0)]);
+        assert_eq!(what_if.fewest_visited, vec![(0, 1)]);
+        assert_eq!(what_if.loop_breakers.len(), 4);
+
+        let input = parse_input(TEST_INPUT).unwrap();
+        let what_if = what_if_removed(&input);
+        assert_eq!(what_if.baseline, Outcome::Exits { visited: 41 });
+        assert!(what_if.loop_breakers.is_empty());
+        // Same as trying every obstacle
+        let all = input
+            .obstacles
+            .iter()
+            .map(|&obstacle| {
+                let mut removed = input.clone();
+                removed.obstacles.remove(&obstacle);
+                outcome(&removed)
+            })
+            .filter_map(|o| match o {
+                Outcome::Exits { visited } => Some(visited),
+                Outcome::Loops => None,
+            })
+            .collect::<Vec<_>>();
+        let most = what_if
+            .removals
+            .iter()
+            .find(|(p, _)| *p == what_if.most_visited[0]);
+        assert_eq!(
+            most.unwrap().1,
+            Outcome::Exits {
+                visited: *all.iter().max().unwrap()
+            }
+        );
+        let fewest = what_if
+            .removals
+            .iter()
+            .find(|(p, _)| *p == what_if.fewest_visited[0]);
+        assert_eq!(
+            fewest.unwrap().1,
+            Outcome::Exits {
+                visited: *all.iter().min().unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert_eq!(
+            diagnostics(&LOOP_INPUT.replace('>', ".")),
+            "As is, the guard loops
+Most visited: removing [(2, 0)] exits after visiting 5 cells
+Fewest visited: removing [(0, 1)] exits after visiting 2 cells
+Loop breakers: [(0, 1), (1, 3), (3, 2), (2, 0)]"
+        );
+        assert_eq!(
+            diagnostics(LOOP_INPUT),
+            "Guard ^ at (1, 1): loops
+Guard > at (3, 3): exits after 1 ticks
+5 cells visited"
+        );
+        assert_eq!(diagnostics("..."), "no guard on the map");
+    }
+
+    #[test]
+    fn test_part2_parallel() {
+        let input = parse_input(TEST_INPUT).unwrap();
+        assert_eq!(part2_parallel(&input), 6);
+
+        let expected = candidates(&input)
+            .into_iter()
+            .map(|c| c.obstacle)
+            .filter(|p| {
+                let mut obstacles = input.obstacles.clone();
+                obstacles.insert(*p);
+                does_loop(
+                    &input.starting_point,
+                    &input.starting_direction,
+                    &obstacles,
+                    input.height,
+                    input.width,
+                )
+            })
+            .collect::<Vec<_>>();
+        for threads in [0, 1, 2, 3, 7, 64] {
+            assert_eq!(loop_obstacles_parallel(&input, threads), expected);
+        }
     }
 }
@@ -1,40 +1,203 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
+use num_bigint::{BigInt, Sign};
 use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// Above this the wide variant gives up on exponents
+const MAX_BITS: u64 = 1 << 16;
+
+/// The integers the operators work on.
+trait Number: Clone + Ord + fmt::Display + FromStr + Sync + 'static {
+    fn from_u32(n: u32) -> Self;
+    fn to_u32(&self) -> Option<u32>;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn checked_div(&self, other: &Self) -> Option<Self>; // rounding towards zero
+    fn checked_rem(&self, other: &Self) -> Option<Self>;
+    fn checked_pow(&self, exp: u32) -> Option<Self>;
+}
+
+macro_rules! number {
+    ($($t:ty),*) => {$(
+        impl Number for $t {
+            fn from_u32(n: u32) -> Self {
+                n.into()
+            }
+
+            fn to_u32(&self) -> Option<u32> {
+                u32::try_from(*self).ok()
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_sub(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_sub(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn checked_div(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_div(*self, *other)
+            }
+
+            fn checked_rem(&self, other: &Self) -> Option<Self> {
+                <$t>::checked_rem(*self, *other)
+            }
+
+            fn checked_pow(&self, exp: u32) -> Option<Self> {
+                <$t>::checked_pow(*self, exp)
+            }
+        }
+    )*};
+}
+
+number!(i64, i128);
+
+impl Number for BigInt {
+    fn from_u32(n: u32) -> Self {
+        n.into()
+    }
+
+    fn to_u32(&self) -> Option<u32> {
+        u32::try_from(self).ok()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_sub(&self, other: &Self) -> Option<Self> {
+        Some(self - other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn checked_div(&self, other: &Self) -> Option<Self> {
+        (other.sign() != Sign::NoSign).then(|| self / other)
+    }
+
+    fn checked_rem(&self, other: &Self) -> Option<Self> {
+        (other.sign() != Sign::NoSign).then(|| self % other)
+    }
+
+    fn checked_pow(&self, exp: u32) -> Option<Self> {
+        (self.bits() * exp as u64 <= MAX_BITS).then(|| self.pow(exp))
+    }
+}
 
 #[derive(Debug, Clone)]
-struct Equation {
-    target: i64,
-    values: Vec<i64>,
+struct Equation<N = i64> {
+    target: N,
+    values: Vec<N>,
 }
 
-#[aoc_generator(day7)]
-fn parse_input(input: &str) -> Vec<Equation> {
+#[derive(Debug, PartialEq)]
+enum CalibrationError {
+    Invalid { line: usize, token: String },
+    TooLarge { line: usize, token: String },
+    TotalTooLarge,
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalibrationError::Invalid { line, token } => {
+                write!(f, "line {}: {:?} is not a number", line, token)
+            }
+            CalibrationError::TooLarge { line, token } => write!(
+                f,
+                "line {}: {} is too large, the wide variant takes any size",
+                line, token
+            ),
+            CalibrationError::TotalTooLarge => {
+                write!(f, "the total is too large, the wide variant takes any size")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {}
+
+fn parse_equations<N: Number>(input: &str) -> Result<Vec<Equation<N>>, CalibrationError> {
     input
         .lines()
-        .map(|line| {
-            let (target, rest) = line.split(':').collect_tuple().expect("bad input");
+        .enumerate()
+        .map(|(i, line)| {
+            let line_number = i + 1;
+            let invalid = |token: &str| CalibrationError::Invalid {
+                line: line_number,
+                token: token.to_string(),
+            };
+            let number = |token: &str| {
+                let token = token.trim();
+                token.parse::<N>().map_err(|_| {
+                    let digits = token.strip_prefix('-').unwrap_or(token);
+                    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                        CalibrationError::TooLarge {
+                            line: line_number,
+                            token: token.to_string(),
+                        }
+                    } else {
+                        invalid(token)
+                    }
+                })
+            };
 
+            let (target, rest) = line.split_once(':').ok_or_else(|| invalid(line))?;
             let values = rest
                 .split_whitespace()
-                .map(|n| n.parse().expect("bad input"))
-                .collect();
+                .map(number)
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.is_empty() {
+                return Err(invalid(line));
+            }
 
-            Equation {
-                target: target.trim().parse().unwrap(),
+            Ok(Equation {
+                target: number(target)?,
                 values,
-            }
+            })
         })
         .collect()
 }
 
+#[aoc_generator(day7)]
+fn parse_input(input: &str) -> Result<Vec<Equation>, CalibrationError> {
+    parse_equations(input)
+}
+
+#[aoc_generator(day7, part1, wide)]
+#[aoc_generator(day7, part2, wide)]
+fn parse_wide(input: &str) -> Result<Vec<Equation<BigInt>>, CalibrationError> {
+    parse_equations(input)
+}
+
+// Adding, multiplying or concatenating numbers of at least 1 never gives less, so results above
+// the target are dead
+fn within_target(eq: &Equation) -> impl Fn(&i64) -> bool {
+    let prune = eq.values.iter().all(|&v| v >= 1);
+    let target = eq.target;
+    move |&result| !prune || result <= target
+}
+
 fn is_possible(eq: &Equation) -> bool {
     let (first, rest) = eq.values.split_first().unwrap();
 
     let acc = HashSet::from([*first]);
 
     let possible_results: HashSet<i64> = rest.iter().fold(acc, |acc, cur| {
-        acc.iter().flat_map(|&v| [v + cur, v * cur]).collect()
+        acc.iter()
+            .flat_map(|&v| [v.checked_add(*cur), v.checked_mul(*cur)])
+            .flatten()
+            .filter(within_target(eq))
+            .collect()
     });
     possible_results.contains(&eq.target)
 }
@@ -47,11 +210,13 @@ fn is_possible_with_concat(eq: &Equation) -> bool {
     let possible_results: HashSet<i64> = rest.iter().fold(acc, |acc, cur| {
         acc.iter()
             .flat_map(|&v| {
-                [v + cur, v * cur, {
+                [v.checked_add(*cur), v.checked_mul(*cur), {
                     let s = format!("{}{}", v, cur);
-                    s.parse().unwrap()
+                    s.parse().ok()
                 }]
             })
+            .flatten()
+            .filter(within_target(eq))
             .collect()
     });
     possible_results.contains(&eq.target)
@@ -65,19 +230,426 @@ fn is_possible_with_concat_faster(eq: &Equation) -> bool {
     let possible_results: HashSet<i64> = rest.iter().fold(acc, |acc, cur| {
         acc.iter()
             .flat_map(|&v| {
-                [v + cur, v * cur, {
-                    let num_digits = cur.ilog10() + 1;
-                    let multiplier = 10_i64.pow(num_digits);
-                    v * multiplier + cur
+                [v.checked_add(*cur), v.checked_mul(*cur), {
+                    // 0 has one digit too
+                    let num_digits = cur.checked_ilog10().unwrap_or(0) + 1;
+                    10_i64
+                        .checked_pow(num_digits)
+                        .and_then(|multiplier| v.checked_mul(multiplier))
+                        .and_then(|shifted| shifted.checked_add(*cur))
                 }]
             })
+            .flatten()
+            .filter(within_target(eq))
             .collect()
     });
     possible_results.contains(&eq.target)
 }
 
+trait Operator<N: Number>: Sync {
+    fn symbol(&self) -> String;
+
+    /// None when the result is undefined or doesn't fit.
+    fn eval(&self, left: &N, right: &N) -> Option<N>;
+
+    /// Every left operand giving `result` with `right`. None when the operator can't tell, the
+    /// solver then searches forwards.
+    fn inverse(&self, _result: &N, _right: &N) -> Option<Vec<N>> {
+        None
+    }
+
+    /// Whether natural numbers only give natural numbers, so negative targets can be pruned.
+    fn keeps_natural(&self) -> bool {
+        false
+    }
+
+    /// Whether the result is at least the left operand when both are at least 1, so results
+    /// above the target can be pruned.
+    fn grows(&self) -> bool {
+        false
+    }
+}
+
+struct Add;
+struct Mul;
+struct Concat {
+    base: u32, // at least 2
+}
+// Not in the puzzle
+struct Sub;
+struct Div; // rounding towards zero
+struct Pow;
+
+#[derive(Debug, PartialEq)]
+struct InvalidBase(u32);
+
+impl fmt::Display for InvalidBase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid base {}, expected at least 2", self.0)
+    }
+}
+
+impl std::error::Error for InvalidBase {}
+
+impl<N: Number> Operator<N> for Add {
+    fn symbol(&self) -> String {
+        "+".to_string()
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_add(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Option<Vec<N>> {
+        Some(result.checked_sub(right).into_iter().collect())
+    }
+
+    fn keeps_natural(&self) -> bool {
+        true
+    }
+
+    fn grows(&self) -> bool {
+        true
+    }
+}
+
+impl<N: Number> Operator<N> for Mul {
+    fn symbol(&self) -> String {
+        "*".to_string()
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_mul(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Option<Vec<N>> {
+        let zero = N::from_u32(0);
+        if *right == zero {
+            // Anything times 0
+            return (*result != zero).then(Vec::new);
+        }
+        if result.checked_rem(right)? != zero {
+            return Some(vec![]);
+        }
+        Some(result.checked_div(right).into_iter().collect())
+    }
+
+    fn keeps_natural(&self) -> bool {
+        true
+    }
+
+    fn grows(&self) -> bool {
+        true
+    }
+}
+
+impl Concat {
+    // Part 2
+    const DECIMAL: Concat = Concat { base: 10 };
+
+    fn new(base: u32) -> Result<Concat, InvalidBase> {
+        match base {
+            0 | 1 => Err(InvalidBase(base)),
+            _ => Ok(Concat { base }),
+        }
+    }
+
+    // base^digits of n, 0 has one digit
+    fn shift<N: Number>(&self, n: &N) -> Option<N> {
+        let base = N::from_u32(self.base);
+        let mut shift = base.clone();
+        while shift <= *n {
+            shift = shift.checked_mul(&base)?;
+        }
+        Some(shift)
+    }
+}
+
+impl<N: Number> Operator<N> for Concat {
+    fn symbol(&self) -> String {
+        match self.base {
+            10 => "||".to_string(),
+            base => format!("|{}|", base),
+        }
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        let zero = N::from_u32(0);
+        if *left < zero || *right < zero {
+            return None;
+        }
+        left.checked_mul(&self.shift(right)?)?.checked_add(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Option<Vec<N>> {
+        let zero = N::from_u32(0);
+        let left = (|| {
+            if *right < zero || result < right {
+                return None;
+            }
+            let shift = self.shift(right)?;
+            let rest = result.checked_sub(right)?;
+            (rest.checked_rem(&shift)? == zero).then(|| rest.checked_div(&shift))?
+        })();
+        Some(left.into_iter().collect())
+    }
+
+    fn keeps_natural(&self) -> bool {
+        true
+    }
+
+    fn grows(&self) -> bool {
+        true
+    }
+}
+
+impl<N: Number> Operator<N> for Sub {
+    fn symbol(&self) -> String {
+        "-".to_string()
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_sub(right)
+    }
+
+    fn inverse(&self, result: &N, right: &N) -> Option<Vec<N>> {
+        Some(result.checked_add(right).into_iter().collect())
+    }
+}
+
+impl<N: Number> Operator<N> for Div {
+    fn symbol(&self) -> String {
+        "/".to_string()
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_div(right)
+    }
+
+    fn keeps_natural(&self) -> bool {
+        true
+    }
+}
+
+impl<N: Number> Operator<N> for Pow {
+    fn symbol(&self) -> String {
+        "^".to_string()
+    }
+
+    fn eval(&self, left: &N, right: &N) -> Option<N> {
+        left.checked_pow(right.to_u32()?)
+    }
+
+    fn keeps_natural(&self) -> bool {
+        true
+    }
+
+    fn grows(&self) -> bool {
+        true
+    }
+}
+
+type Operators<'a, N> = [&'a dyn Operator<N>];
+
+// Tried in order, the first solution found is returned
+fn part1_operators<N: Number>() -> Vec<&'static dyn Operator<N>> {
+    vec![&Mul, &Add]
+}
+
+fn part2_operators<N: Number>() -> Vec<&'static dyn Operator<N>> {
+    vec![&Mul, &Concat::DECIMAL, &Add]
+}
+
+/// `10*19`, evaluated left to right. The solution holds indices into the operators.
+fn expression<N: Number>(values: &[N], ops: &Operators<N>, solution: &[usize]) -> String {
+    let mut s = values[0].to_string();
+    for (&op, value) in solution.iter().zip(&values[1..]) {
+        s.push_str(&ops[op].symbol());
+        s.push_str(&value.to_string());
+    }
+    s
+}
+
+struct Search<'a, N: Number> {
+    ops: &'a Operators<'a, N>,
+    natural: bool, // no negative results
+    growing: bool, // no results going down
+}
+
+impl<N: Number> Search<'_, N> {
+    /// Every result of the values with every choice of operators, with the choice. Results above
+    /// the limit are dropped when they can't come down again.
+    fn forward(&self, values: &[N], limit: &N) -> Vec<(N, Vec<usize>)> {
+        let (first, rest) = values.split_first().unwrap();
+        rest.iter()
+            .fold(vec![(first.clone(), vec![])], |acc, value| {
+                acc.iter()
+                    .flat_map(|(result, solution)| {
+                        self.ops.iter().enumerate().filter_map(move |(i, op)| {
+                            let result = op.eval(result, value)?;
+                            if self.growing && result > *limit {
+                                return None;
+                            }
+                            let mut solution = solution.clone();
+                            solution.push(i);
+                            Some((result, solution))
+                        })
+                    })
+                    .collect()
+            })
+    }
+
+    fn undo(&self, target: &N, values: &[N]) -> Option<Vec<usize>> {
+        if self.natural && *target < N::from_u32(0) {
+            return None;
+        }
+        let (last, rest) = values.split_last().unwrap();
+        if rest.is_empty() {
+            return (target == last).then(Vec::new);
+        }
+
+        for (i, op) in self.ops.iter().enumerate() {
+            let found = match op.inverse(target, last) {
+                Some(lefts) => lefts.iter().find_map(|left| self.undo(left, rest)),
+                None => self
+                    .forward(rest, target)
+                    .into_iter()
+                    .find(|(left, _)| op.eval(left, last).as_ref() == Some(target))
+                    .map(|(_, solution)| solution),
+            };
+            if let Some(mut solution) = found {
+                solution.push(i);
+                return Some(solution);
+            }
+        }
+        None
+    }
+}
+
+/// Works back from the target, undoing the last operator while the rest can still produce what
+/// is left: `*` needs a multiple, `||` the digits at the end and `+` a large enough number.
+/// Operators without an inverse fall back to trying all the results of the rest. Arithmetic is
+/// checked, a branch that overflows is dead.
+fn solve<N: Number>(target: &N, values: &[N], ops: &Operators<N>) -> Option<Vec<usize>> {
+    let one = N::from_u32(1);
+    let search = Search {
+        ops,
+        natural: values.iter().all(|v| *v >= N::from_u32(0))
+            && ops.iter().all(|op| op.keeps_natural()),
+        growing: values.iter().all(|v| *v >= one) && ops.iter().all(|op| op.grows()),
+    };
+    search.undo(target, values)
+}
+
+fn total<N: Number>(input: &[Equation<N>], ops: &Operators<N>) -> Result<N, CalibrationError> {
+    input
+        .iter()
+        .filter(|eq| solve(&eq.target, &eq.values, ops).is_some())
+        .try_fold(N::from_u32(0), |total, eq| total.checked_add(&eq.target))
+        .ok_or(CalibrationError::TotalTooLarge)
+}
+
+const MAX_EXTENDED_VALUES: usize = 6;
+
+/// A solution for every equation that has one, with the operators of part 2, and how many can be
+/// solved with other operators.
+pub(crate) fn diagnostics(input: &str) -> String {
+    let equations = match parse_equations::<BigInt>(input) {
+        Ok(equations) => equations,
+        Err(e) => return e.to_string(),
+    };
+    let ops = part2_operators();
+    let mut unsolved = 0;
+    let mut lines = vec![];
+    for eq in &equations {
+        match solve(&eq.target, &eq.values, &ops) {
+            Some(solution) => lines.push(format!(
+                "{} = {}",
+                eq.target,
+                expression(&eq.values, &ops, &solution)
+            )),
+            None => unsolved += 1,
+        }
+    }
+    lines.push(format!(
+        "{} of {} equations have no solution",
+        unsolved,
+        equations.len()
+    ));
+
+    // `/` and `^` can't be undone, so the search goes forwards and only short equations are tried
+    let short = equations
+        .iter()
+        .filter(|eq| eq.values.len() <= MAX_EXTENDED_VALUES)
+        .collect::<Vec<_>>();
+    let octal = Concat::new(8);
+    let sets = [
+        (
+            "Part 2 with - and /",
+            Ok(vec![
+                &Mul as &dyn Operator<BigInt>,
+                &Concat::DECIMAL,
+                &Add,
+                &Sub,
+                &Div,
+            ]),
+        ),
+        (
+            "Part 2 with ^",
+            Ok(vec![
+                &Mul as &dyn Operator<BigInt>,
+                &Concat::DECIMAL,
+                &Add,
+                &Pow,
+            ]),
+        ),
+        (
+            "Part 2 with octal ||",
+            octal
+                .as_ref()
+                .map(|octal| vec![&Mul as &dyn Operator<BigInt>, octal, &Add]),
+        ),
+    ];
+    for (name, ops) in sets {
+        match ops {
+            Ok(ops) => lines.push(format!(
+                "{}: {} of {} equations with at most {} values have a solution",
+                name,
+                short
+                    .iter()
+                    .filter(|eq| solve(&eq.target, &eq.values, &ops).is_some())
+                    .count(),
+                short.len(),
+                MAX_EXTENDED_VALUES
+            )),
+            Err(e) => lines.push(format!("{}: {}", name, e)),
+        }
+    }
+    lines.join("\n")
+}
+
 #[aoc(day7, part1)]
-fn part1(input: &[Equation]) -> i64 {
+fn part1(input: &[Equation]) -> Result<i64, CalibrationError> {
+    total(input, &part1_operators())
+}
+
+#[aoc(day7, part2)]
+fn part2(input: &[Equation]) -> Result<i64, CalibrationError> {
+    total(input, &part2_operators())
+}
+
+#[aoc(day7, part1, wide)]
+fn part1_wide(input: &[Equation<BigInt>]) -> Result<BigInt, CalibrationError> {
+    total(input, &part1_operators())
+}
+
+#[aoc(day7, part2, wide)]
+fn part2_wide(input: &[Equation<BigInt>]) -> Result<BigInt, CalibrationError> {
+    total(input, &part2_operators())
+}
+
+#[aoc(day7, part1, forward)]
+fn part1_forward(input: &[Equation]) -> i64 {
     input
         .iter()
         .filter(|eq| is_possible(eq))
@@ -85,8 +657,8 @@ fn part1(input: &[Equation]) -> i64 {
         .sum()
 }
 
-#[aoc(day7, part2)]
-fn part2(input: &[Equation]) -> i64 {
+#[aoc(day7, part2, forward)]
+fn part2_forward(input: &[Equation]) -> i64 {
     input
         .iter()
         .filter(|eq| is_possible_with_concat(eq))
@@ -120,16 +692,205 @@ mod tests {
 
     #[test]
     fn test_part1() {
-        assert_eq!(part1(&parse_input(TEST_INPUT)), 3749);
+        assert_eq!(part1(&parse_input(TEST_INPUT).unwrap()), Ok(3749));
     }
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&parse_input(TEST_INPUT)), 11387);
+        assert_eq!(part2(&parse_input(TEST_INPUT).unwrap()), Ok(11387));
     }
 
     #[test]
     fn test_part2_faster() {
-        assert_eq!(part2_faster(&parse_input(TEST_INPUT)), 11387);
+        assert_eq!(part2_faster(&parse_input(TEST_INPUT).unwrap()), 11387);
+    }
+
+    #[test]
+    fn test_forward_variants() {
+        let input = parse_input(TEST_INPUT).unwrap();
+        assert_eq!(part1_forward(&input), 3749);
+        assert_eq!(part2_forward(&input), 11387);
+    }
+
+    #[test]
+    fn test_wide() {
+        let input = parse_wide(TEST_INPUT).unwrap();
+        assert_eq!(part1_wide(&input), Ok(BigInt::from(3749)));
+        assert_eq!(part2_wide(&input), Ok(BigInt::from(11387)));
+
+        // 2^62 * 2^62 * 8 * 3 and 2^63 || 8
+        let long =
+            "510423550381407695195061911147652317184: 4611686018427387904 4611686018427387904 8 3
+92233720368547758088: 9223372036854775808 8
+";
+        assert_eq!(
+            parse_input(long).unwrap_err(),
+            CalibrationError::TooLarge {
+                line: 1,
+                token: "510423550381407695195061911147652317184".to_string()
+            }
+        );
+        let input = parse_wide(long).unwrap();
+        assert_eq!(
+            part2_wide(&input).unwrap().to_string(),
+            "510423550381407695287295631516200075272"
+        );
+        // Past i128 too
+        assert!(parse_equations::<i128>(long).is_err());
+        let (_, second) = long.split_once('\n').unwrap();
+        let input = parse_equations::<i128>(second).unwrap();
+        assert_eq!(total(&input, &part2_operators()), Ok(92233720368547758088));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = parse_input(
+            "9223372036854775807: 9223372036854775807 1
+5: 9223372036854775807 9223372036854775807 5
+1: 99999999999 999999999 0 1
+",
+        )
+        .unwrap();
+        assert_eq!(part1(&input[..1]), Ok(9223372036854775807));
+        assert_eq!(part2(&input[1..]), Ok(1));
+        assert_eq!(part2_forward(&input[1..]), 1);
+        assert_eq!(part2_faster(&input[1..]), 1);
+        assert_eq!(
+            part1(&[input[0].clone(), input[0].clone()]),
+            Err(CalibrationError::TotalTooLarge)
+        );
+        assert_eq!(
+            parse_input("12: 1 x").unwrap_err().to_string(),
+            "line 1: \"x\" is not a number"
+        );
+        assert!(parse_input("12:").is_err());
+    }
+
+    #[test]
+    fn test_zero_concat() {
+        let input = parse_input("100: 10 0\n5: 0 5\n0: 0 0\n").unwrap();
+        assert_eq!(part2(&input), Ok(105));
+        assert_eq!(part2_forward(&input), 105);
+        assert_eq!(part2_faster(&input), 105);
+    }
+
+    #[test]
+    fn test_witness() {
+        let witness = |target: i64, values: &[i64], ops: &Operators<i64>| {
+            solve(&target, values, ops).map(|solution| expression(values, ops, &solution))
+        };
+        let part1 = part1_operators();
+        let part2 = part2_operators();
+        assert_eq!(witness(190, &[10, 19], &part1).as_deref(), Some("10*19"));
+        assert_eq!(
+            witness(3267, &[81, 40, 27], &part1).as_deref(),
+            Some("81+40*27")
+        );
+        assert_eq!(
+            witness(7290, &[6, 8, 6, 15], &part2).as_deref(),
+            Some("6*8||6*15")
+        );
+        assert_eq!(witness(156, &[15, 6], &part2).as_deref(), Some("15||6"));
+        assert_eq!(witness(83, &[17, 5], &part2), None);
+
+        // Zeros
+        assert_eq!(witness(0, &[3, 4, 0], &part1).as_deref(), Some("3*4*0"));
+        assert_eq!(witness(30, &[3, 0], &part2).as_deref(), Some("3||0"));
+        assert_eq!(witness(7, &[0, 7], &part2).as_deref(), Some("0||7"));
+    }
+
+    #[test]
+    fn test_other_operators() {
+        let witness = |target: i64, values: &[i64], ops: &Operators<i64>| {
+            solve(&target, values, ops).map(|solution| expression(values, ops, &solution))
+        };
+        assert_eq!(
+            witness(-5, &[2, 3, 4], &[&Sub, &Add]).as_deref(),
+            Some("2-3-4")
+        );
+        assert_eq!(
+            witness(2, &[9, 4, 1], &[&Div, &Add]).as_deref(),
+            Some("9/4/1")
+        );
+        assert_eq!(
+            witness(81, &[3, 2, 2], &[&Pow, &Mul]).as_deref(),
+            Some("3^2^2")
+        );
+        // 0b101 concatenated with 0b11 is 0b10111
+        assert_eq!(
+            witness(23, &[5, 3], &[&Concat::new(2).unwrap()]).as_deref(),
+            Some("5|2|3")
+        );
+        assert_eq!(Concat::new(1).err(), Some(InvalidBase(1)));
+        assert!(Concat::new(0).is_err());
+        assert_eq!(witness(1, &[4, 0], &[&Div]), None);
+    }
+
+    // Every result, without pruning
+    fn brute(values: &[i64], ops: &Operators<i64>) -> Vec<i64> {
+        values[1..].iter().fold(vec![values[0]], |acc, x| {
+            acc.iter()
+                .flat_map(|v| ops.iter().filter_map(move |op| op.eval(v, x)))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_solve_matches_brute_force() {
+        let base3 = Concat::new(3).unwrap();
+        let sets: [Vec<&dyn Operator<i64>>; 5] = [
+            part1_operators(),
+            part2_operators(),
+            vec![&Sub, &Mul, &Add],
+            vec![&Div, &base3, &Mul],
+            vec![&Pow, &Add, &Sub],
+        ];
+        let mut seed: u64 = 7;
+        let mut next = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+        for _ in 0..300 {
+            let values = (0..2 + next(4))
+                .map(|_| next(20) as i64)
+                .collect::<Vec<_>>();
+            let target = next(2000) as i64 - 100;
+            for ops in &sets {
+                let found = brute(&values, ops).contains(&target);
+                let solution = solve(&target, &values, ops);
+                assert_eq!(solution.is_some(), found, "{:?} {}", values, target);
+                if let Some(solution) = solution {
+                    let result = solution
+                        .iter()
+                        .zip(&values[1..])
+                        .try_fold(values[0], |v, (&op, x)| ops[op].eval(&v, x));
+                    assert_eq!(result, Some(target));
+                }
+            }
+        }
+
+        // Same as the original part 2
+        for eq in parse_input(TEST_INPUT).unwrap() {
+            assert_eq!(
+                solve(&eq.target, &eq.values, &part2_operators()).is_some(),
+                is_possible_with_concat(&eq)
+            );
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let report = diagnostics(TEST_INPUT);
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "190 = 10*19");
+        assert_eq!(lines[3], "7290 = 6*8||6*15");
+        assert_eq!(lines[6], "3 of 9 equations have no solution");
+        assert_eq!(
+            lines[7],
+            "Part 2 with - and /: 6 of 9 equations with at most 6 values have a solution"
+        );
+        assert_eq!(lines.len(), 10);
     }
 }
@@ -1,22 +1,152 @@
+use crate::report::Render;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
-use std::ops::Range;
+use std::error::Error;
+use std::fmt;
 
 type Coord = (i32, i32);
 type AntennaGroups = HashMap<char, HashSet<Coord>>;
 
 struct Input {
     antenna_groups: AntennaGroups,
-    range: Range<i32>,
+    height: i32,
+    width: i32,
+}
+
+/// The antenna pair that created an antinode. The pair is in reading order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Source {
+    frequency: char,
+    pair: (Coord, Coord),
+}
+
+type Provenance = HashMap<Coord, Vec<Source>>;
+
+/// Where a pair of antennas creates antinodes, measured in steps along the line through the pair.
+/// A step is the delta between the antennas, or with `reduce` the delta divided by its gcd so that
+/// grid points between the antennas can count as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Resonance {
+    /// Distances to the nearer and the farther antenna, `None` for every point in line
+    ratio: Option<(u32, u32)>,
+    /// Antinodes past each antenna at multiples of the first one's distance, `None` until the edge
+    harmonics: Option<u32>,
+    reduce: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct InvalidRatio(u32, u32);
+
+impl fmt::Display for InvalidRatio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid ratio {}:{}, expected near:far with 0 < near <= far",
+            self.0, self.1
+        )
+    }
+}
+
+impl Error for InvalidRatio {}
+
+impl Resonance {
+    // Part 1
+    const TWICE_AS_FAR: Resonance = Resonance {
+        ratio: Some((1, 2)),
+        harmonics: Some(1),
+        reduce: false,
+    };
+    // Part 2
+    const IN_LINE: Resonance = Resonance {
+        ratio: None,
+        harmonics: None,
+        reduce: false,
+    };
+
+    fn new(
+        ratio: Option<(u32, u32)>,
+        harmonics: Option<u32>,
+        reduce: bool,
+    ) -> Result<Resonance, InvalidRatio> {
+        match ratio {
+            Some((near, far)) if near == 0 || near > far => Err(InvalidRatio(near, far)),
+            _ => Ok(Resonance {
+                ratio,
+                harmonics,
+                reduce,
+            }),
+        }
+    }
+
+    fn anti_nodes(&self, a: &Coord, b: &Coord, height: i32, width: i32) -> Vec<Coord> {
+        let (dy, dx) = (a.0 as i64 - b.0 as i64, a.1 as i64 - b.1 as i64);
+        let distance = if self.reduce { gcd(dy, dx) } else { 1 }; // in steps
+        let step = (dy / distance, dx / distance);
+        let at = |from: &Coord, t: i64| (from.0 as i64 + t * step.0, from.1 as i64 + t * step.1);
+        let inside =
+            |(y, x): (i64, i64)| (0..height as i64).contains(&y) && (0..width as i64).contains(&x);
+
+        let mut coords = vec![];
+
+        // Outwards from each antenna the k-th harmonic is k * num / den steps away, from k = first
+        let (outwards, first) = match self.ratio {
+            None => {
+                coords.extend((1..distance).map(|t| at(b, t)));
+                (Some((1, 1)), 0)
+            }
+            Some((near, far)) => {
+                let (near, far) = (near as i64, far as i64);
+                let between = near * distance;
+                if between % (near + far) == 0 {
+                    coords.push(at(a, -between / (near + far)));
+                    coords.push(at(b, between / (near + far)));
+                }
+                ((near < far).then_some((near * distance, far - near)), 1)
+            }
+        };
+
+        if let Some((num, den)) = outwards {
+            // Only every `period`-th harmonic lands on the grid, `advance` steps further each time
+            let period = den / gcd(num, den);
+            let advance = num / gcd(num, den);
+            for (from, direction) in [(a, 1), (b, -1)] {
+                for j in first.. {
+                    if self.harmonics.is_some_and(|h| j * period > h as i64) {
+                        break;
+                    }
+                    let p = at(from, direction * j * advance);
+                    if !inside(p) {
+                        break;
+                    }
+                    coords.push(p);
+                }
+            }
+        }
+
+        coords
+            .into_iter()
+            .map(|(y, x)| (y as i32, x as i32))
+            .collect()
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
 }
 
 #[aoc_generator(day8)]
 fn parse_input(input: &str) -> Input {
     let mut len_y = 0;
+    let mut len_x = 0;
     let mut antennae = HashMap::new();
     for (y, line) in input.lines().enumerate() {
         len_y += 1;
+        len_x = len_x.max(line.chars().count() as i32);
         for (x, char) in line.chars().enumerate() {
             if char == '.' {
                 continue;
@@ -30,77 +160,144 @@ fn parse_input(input: &str) -> Input {
 
     Input {
         antenna_groups: antennae,
-        range: 0..len_y,
+        height: len_y,
+        width: len_x,
     }
 }
 
-fn get_anti_nodes(antennae: &HashSet<Coord>, range: &Range<i32>) -> HashSet<Coord> {
-    let pairs = antennae.iter().tuple_combinations();
-
-    pairs
-        .flat_map(|(a, b)| {
-            let dy = a.0 - b.0;
-            let dx = a.1 - b.1;
-
-            vec![(a.0 + dy, a.1 + dx), (b.0 - dy, b.1 - dx)]
-        })
-        .filter(|p| range.contains(&p.0) && range.contains(&p.1))
+fn get_anti_nodes(
+    antennae: &HashSet<Coord>,
+    resonance: &Resonance,
+    height: i32,
+    width: i32,
+) -> HashSet<Coord> {
+    antennae
+        .iter()
+        .tuple_combinations()
+        .flat_map(|(a, b)| resonance.anti_nodes(a, b, height, width))
         .collect()
 }
 
-fn get_repeating_anti_nodes(antennae: &HashSet<Coord>, range: &Range<i32>) -> HashSet<Coord> {
-    let pairs = antennae.iter().tuple_combinations();
-
-    let in_range = |(y, x)| range.contains(&y) && range.contains(&x);
-
-    pairs
-        .flat_map(|(a, b)| {
-            let dy = a.0 - b.0;
-            let dx = a.1 - b.1;
-
-            let mut coords = vec![];
-
-            for m in 0.. {
-                let p = (a.0 + m * dy, a.1 + m * dx);
-                if !in_range(p) {
-                    break;
+/// Every antinode with the pairs that created it, sorted by frequency and pair.
+fn provenance(input: &Input, resonance: &Resonance) -> Provenance {
+    let mut provenance: Provenance = HashMap::new();
+    for (&frequency, antennae) in &input.antenna_groups {
+        for pair in antennae.iter().sorted().tuple_combinations() {
+            let (a, b) = pair;
+            for p in resonance.anti_nodes(a, b, input.height, input.width) {
+                let sources = provenance.entry(p).or_default();
+                let source = Source {
+                    frequency,
+                    pair: (*a, *b),
+                };
+                // A line can pass through the same point twice, e.g. at an antenna
+                if !sources.contains(&source) {
+                    sources.push(source);
                 }
-                coords.push(p);
             }
+        }
+    }
+    for sources in provenance.values_mut() {
+        sources.sort();
+    }
+    provenance
+}
 
-            for m in 0.. {
-                let p = (a.0 - m * dy, a.1 - m * dx);
-                if !in_range(p) {
-                    break;
-                }
-                coords.push(p);
+/// Which pairs produced the antinode at `p`.
+fn produced_by(provenance: &Provenance, p: Coord) -> &[Source] {
+    provenance.get(&p).map_or(&[], |sources| sources.as_slice())
+}
+
+pub(crate) fn diagnostics(input: &str) -> String {
+    let input = parse_input(input);
+    let mut lines = vec![format!(
+        "{} antennas on {} frequencies, {}x{} map",
+        input
+            .antenna_groups
+            .values()
+            .map(|a| a.len())
+            .sum::<usize>(),
+        input.antenna_groups.len(),
+        input.width,
+        input.height
+    )];
+    for (name, resonance) in [
+        ("Part 1", Ok(Resonance::TWICE_AS_FAR)),
+        ("Part 2", Ok(Resonance::IN_LINE)),
+        // Whether antennas with a common factor in their delta matter for the input
+        (
+            "Part 1 between grid points",
+            Resonance::new(Some((1, 2)), Some(1), true),
+        ),
+        (
+            "Part 2 between grid points",
+            Resonance::new(None, None, true),
+        ),
+    ] {
+        let resonance = match resonance {
+            Ok(resonance) => resonance,
+            Err(e) => {
+                lines.push(format!("{}: {}", name, e));
+                continue;
             }
+        };
+        let provenance = provenance(&input, &resonance);
+        let shared = provenance.values().filter(|s| s.len() > 1).count();
+        lines.push(format!(
+            "{}: {} antinodes, {} created by more than one pair",
+            name,
+            provenance.len(),
+            shared
+        ));
+        let busiest = provenance
+            .keys()
+            .max_by_key(|&&p| (produced_by(&provenance, p).len(), std::cmp::Reverse(p)));
+        if let Some(&p) = busiest {
+            let pairs = produced_by(&provenance, p)
+                .iter()
+                .map(|s| format!("{} {:?}-{:?}", s.frequency, s.pair.0, s.pair.1))
+                .join(", ");
+            lines.push(format!("\tBusiest antinode {:?}: {}", p, pairs));
+        }
+    }
+    lines.join("\n")
+}
 
-            coords
-        })
-        .collect()
+pub(crate) fn render(input: &str) -> Option<Render> {
+    let input = parse_input(input);
+    let mut render = Render::new(
+        "Day 8 antennas and antinodes",
+        input.width as usize,
+        input.height as usize,
+    );
+    // Antinodes first so that antennas stay visible on top of them
+    for &(y, x) in provenance(&input, &Resonance::TWICE_AS_FAR).keys() {
+        render.set(x as usize, y as usize, 1);
+    }
+    for &(y, x) in input.antenna_groups.values().flatten() {
+        render.set(x as usize, y as usize, 2);
+    }
+    Some(render)
 }
 
-#[aoc(day8, part1)]
-fn part1(input: &Input) -> i32 {
+fn count_anti_nodes(input: &Input, resonance: &Resonance) -> i32 {
     let anti_nodes = input
         .antenna_groups
         .values()
-        .flat_map(|antennae| get_anti_nodes(antennae, &input.range))
+        .flat_map(|antennae| get_anti_nodes(antennae, resonance, input.height, input.width))
         .collect::<HashSet<Coord>>();
 
     anti_nodes.len() as i32
 }
 
+#[aoc(day8, part1)]
+fn part1(input: &Input) -> i32 {
+    count_anti_nodes(input, &Resonance::TWICE_AS_FAR)
+}
+
 #[aoc(day8, part2)]
 fn part2(input: &Input) -> i32 {
-    let possible_anti_nodes = input
-        .antenna_groups
-        .values()
-        .flat_map(|antennae| get_repeating_anti_nodes(antennae, &input.range))
-        .collect::<HashSet<Coord>>();
-
-    possible_anti_nodes.len() as i32
+    count_anti_nodes(input, &Resonance::IN_LINE)
 }
 
 #[cfg(test)]
@@ -144,30 +341,28 @@ mod tests {
                 ('0', HashSet::from([(1, 8), (2, 5), (3, 7), (4, 4)])),
             ])
         );
-        assert_eq!(parsed.range, 0..12);
+        assert_eq!((parsed.height, parsed.width), (12, 12));
     }
 
     #[test]
     fn test_get_anti_nodes() {
         let antennae1 = HashSet::from([(1, 1), (2, 2)]);
-        let range = 0..4;
         assert_eq!(
-            get_anti_nodes(&antennae1, &range),
+            get_anti_nodes(&antennae1, &Resonance::TWICE_AS_FAR, 4, 4),
             HashSet::from([(0, 0), (3, 3)])
         );
 
         let antennae2 = HashSet::from([(1, 2), (2, 1)]);
         assert_eq!(
-            get_anti_nodes(&antennae2, &range),
+            get_anti_nodes(&antennae2, &Resonance::TWICE_AS_FAR, 4, 4),
             HashSet::from([(0, 3), (3, 0)])
         );
     }
 
     #[test]
-    fn test_get_repeating_anti_nodes() {
+    fn test_get_anti_nodes_in_line() {
         let antennae = HashSet::from([(0, 0), (1, 3), (2, 1)]);
-        let range = 0..10;
-        let result = get_repeating_anti_nodes(&antennae, &range);
+        let result = get_anti_nodes(&antennae, &Resonance::IN_LINE, 10, 10);
         assert_eq!(
             result,
             HashSet::from([
@@ -184,6 +379,149 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resonance() {
+        let anti_nodes = |resonance: Resonance, a, b, width| {
+            let mut coords = resonance.anti_nodes(&a, &b, 10, width);
+            coords.sort();
+            coords.dedup();
+            coords
+        };
+        let rule = |ratio, harmonics| Resonance::new(ratio, harmonics, true).unwrap();
+
+        assert_eq!(
+            anti_nodes(Resonance::TWICE_AS_FAR, (0, 0), (3, 3), 10),
+            vec![(6, 6)]
+        );
+        // Antennas three steps apart have grid points a third of the way between them
+        assert_eq!(
+            anti_nodes(rule(Some((1, 2)), Some(1)), (0, 0), (3, 3), 10),
+            vec![(1, 1), (2, 2), (6, 6)]
+        );
+        assert_eq!(
+            anti_nodes(Resonance::IN_LINE, (0, 0), (2, 2), 5),
+            vec![(0, 0), (2, 2), (4, 4)]
+        );
+        assert_eq!(
+            anti_nodes(rule(None, None), (0, 0), (2, 2), 4),
+            vec![(0, 0), (1, 1), (2, 2), (3, 3)]
+        );
+        assert_eq!(
+            anti_nodes(Resonance::TWICE_AS_FAR, (0, 0), (0, 1), 10),
+            vec![(0, 2)]
+        );
+
+        assert_eq!(
+            anti_nodes(rule(Some((1, 3)), Some(1)), (0, 0), (0, 4), 12),
+            vec![(0, 1), (0, 3), (0, 6)]
+        );
+        assert_eq!(
+            anti_nodes(rule(Some((1, 2)), Some(2)), (0, 0), (0, 1), 10),
+            vec![(0, 2), (0, 3)]
+        );
+        assert_eq!(
+            anti_nodes(rule(Some((2, 3)), None), (0, 0), (0, 1), 10),
+            vec![(0, 3), (0, 5), (0, 7), (0, 9)]
+        );
+        assert_eq!(
+            anti_nodes(rule(Some((1, 1)), None), (0, 0), (0, 2), 10),
+            vec![(0, 1)]
+        );
+        // The first harmonic of 1:3 falls between grid points, the second does not
+        assert!(anti_nodes(rule(Some((1, 3)), Some(1)), (0, 0), (0, 1), 3).is_empty());
+        assert_eq!(
+            anti_nodes(rule(Some((1, 3)), Some(2)), (0, 0), (0, 1), 3),
+            vec![(0, 2)]
+        );
+        // Billions of harmonics fall between grid points before one lands, without walking them
+        assert_eq!(
+            anti_nodes(
+                rule(Some((1, u32::MAX)), Some(u32::MAX - 1)),
+                (0, 0),
+                (0, 1),
+                10
+            ),
+            vec![(0, 2)]
+        );
+
+        assert_eq!(
+            anti_nodes(rule(None, Some(0)), (0, 0), (0, 2), 10),
+            vec![(0, 0), (0, 1), (0, 2)]
+        );
+        // Harmonics are counted in steps, not in multiples of the pair distance
+        assert_eq!(
+            anti_nodes(rule(None, Some(1)), (0, 2), (0, 4), 10),
+            vec![(0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]
+        );
+
+        assert_eq!(
+            Resonance::new(Some((3, 2)), None, true),
+            Err(InvalidRatio(3, 2))
+        );
+        assert_eq!(
+            Resonance::new(Some((0, 1)), None, false)
+                .unwrap_err()
+                .to_string(),
+            "Invalid ratio 0:1, expected near:far with 0 < near <= far"
+        );
+    }
+
+    // The rules of the parts before they became presets
+    fn baseline_anti_nodes(
+        antennae: &HashSet<Coord>,
+        size: i32,
+        repeating: bool,
+    ) -> HashSet<Coord> {
+        let in_range = |(y, x): Coord| (0..size).contains(&y) && (0..size).contains(&x);
+        let mut coords = HashSet::new();
+        for (a, b) in antennae.iter().tuple_combinations() {
+            let (dy, dx) = (a.0 - b.0, a.1 - b.1);
+            if !repeating {
+                coords.extend(
+                    [(a.0 + dy, a.1 + dx), (b.0 - dy, b.1 - dx)]
+                        .into_iter()
+                        .filter(|&p| in_range(p)),
+                );
+                continue;
+            }
+            for sign in [1, -1] {
+                for m in 0.. {
+                    let p = (a.0 + sign * m * dy, a.1 + sign * m * dx);
+                    if !in_range(p) {
+                        break;
+                    }
+                    coords.insert(p);
+                }
+            }
+        }
+        coords
+    }
+
+    #[test]
+    fn test_presets_match_baseline() {
+        // Deltas with a common factor, where the gcd reduction would add points
+        let mut seed = 7u32;
+        let mut next = |n: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((seed >> 16) % n) as i32
+        };
+        for _ in 0..50 {
+            let antennae = (0..5)
+                .map(|_| (next(6) * 2, next(6) * 3))
+                .collect::<HashSet<Coord>>();
+            for (resonance, repeating) in
+                [(Resonance::TWICE_AS_FAR, false), (Resonance::IN_LINE, true)]
+            {
+                assert_eq!(
+                    get_anti_nodes(&antennae, &resonance, 18, 18),
+                    baseline_anti_nodes(&antennae, 18, repeating),
+                    "{:?}",
+                    antennae
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_part1() {
         assert_eq!(part1(&parse_input(TEST_INPUT)), 14);
@@ -194,4 +532,72 @@ mod tests {
         assert_eq!(part2(&parse_input(TEST_INPUT_2)), 9);
         assert_eq!(part2(&parse_input(TEST_INPUT)), 34);
     }
+
+    // Antinodes past the last row but inside the width used to be counted
+    static WIDE_INPUT: &str = "..........
+...a......
+.....a....
+";
+
+    #[test]
+    fn test_rectangular_bounds() {
+        let input = parse_input(WIDE_INPUT);
+        assert_eq!((input.height, input.width), (3, 10));
+        assert_eq!(part1(&input), 1);
+        assert_eq!(
+            provenance(&input, &Resonance::TWICE_AS_FAR)
+                .keys()
+                .collect::<Vec<_>>(),
+            vec![&(0, 1)]
+        );
+
+        let tall = parse_input("..\n..\n.b\n..\n.b\n..\n");
+        assert_eq!((tall.height, tall.width), (6, 2));
+        assert_eq!(part1(&tall), 1);
+        assert_eq!(part2(&tall), 3);
+    }
+
+    #[test]
+    fn test_produced_by() {
+        let input = parse_input(TEST_INPUT);
+        let sources = provenance(&input, &Resonance::TWICE_AS_FAR);
+        assert_eq!(sources.len(), 14);
+        // An A pair and a 0 pair resonate on the same point
+        assert_eq!(
+            produced_by(&sources, (1, 3)),
+            &[
+                Source {
+                    frequency: '0',
+                    pair: ((2, 5), (3, 7)),
+                },
+                Source {
+                    frequency: 'A',
+                    pair: ((5, 6), (9, 9)),
+                },
+            ]
+        );
+        // An antenna of one frequency can be an antinode of another
+        assert_eq!(
+            produced_by(&sources, (5, 6)),
+            &[Source {
+                frequency: '0',
+                pair: ((1, 8), (3, 7)),
+            }]
+        );
+        assert!(produced_by(&sources, (0, 0)).is_empty());
+
+        let repeating = provenance(&input, &Resonance::IN_LINE);
+        assert_eq!(repeating.len(), 34);
+        assert_eq!(produced_by(&repeating, (5, 6)).len(), 3);
+    }
+
+    #[test]
+    fn test_render() {
+        let render = render(WIDE_INPUT).unwrap();
+        assert_eq!((render.width, render.height), (10, 3));
+        assert_eq!(render.get(1, 0), 1);
+        assert_eq!(render.get(3, 1), 2);
+        assert_eq!(render.get(5, 2), 2);
+        assert_eq!(render.cells.iter().filter(|&&c| c != 0).count(), 3);
+    }
 }
@@ -18,7 +18,7 @@ struct Alloc {
 #[aoc_generator(day9, part1)]
 fn parse_input(input: &str) -> Vec<BlockAlloc> {
     // Make sure that the len is even for tuple chunking
-    let input = if input.len() % 2 == 0 {
+    let input = if input.len().is_multiple_of(2) {
         input.to_string()
     } else {
         String::from(input) + "0"
@@ -43,7 +43,7 @@ fn parse_input(input: &str) -> Vec<BlockAlloc> {
 #[aoc_generator(day9, part2)]
 fn parse_input_2(input: &str) -> Vec<Alloc> {
     // Make sure that the len is even for tuple chunking
-    let input = if input.len() % 2 == 0 {
+    let input = if input.len().is_multiple_of(2) {
         input.to_string()
     } else {
         String::from(input) + "0"
@@ -168,10 +168,10 @@ fn part2(input: &[Alloc]) -> u64 {
 
     disk.iter()
         .flat_map(|alloc| {
-            if alloc.id.is_none() {
-                vec![0; alloc.len as usize]
+            if let Some(id) = alloc.id {
+                vec![id; alloc.len as usize]
             } else {
-                vec![alloc.id.unwrap(); alloc.len as usize]
+                vec![0; alloc.len as usize]
             }
         })
         .enumerate()
@@ -0,0 +1,401 @@
+use crate::runner::{input_path, DEFAULT_SET, INPUT_DIR};
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const BASE_URL: &str = "https://adventofcode.com";
+pub const SESSION_FILE: &str = ".session";
+pub const EXAMPLE_SET: &str = "example";
+const USER_AGENT: &str = "github.com/ainkinen/AoC2024 input fetcher";
+
+pub trait HttpClient {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<String, Box<dyn Error>>;
+}
+
+/// HTTPS through an external `curl` binary, which has to be on the `PATH`. Headers go through
+/// stdin to keep the session out of `ps`.
+pub struct CurlClient {
+    pub program: &'static str,
+}
+
+impl Default for CurlClient {
+    fn default() -> Self {
+        CurlClient { program: "curl" }
+    }
+}
+
+impl HttpClient for CurlClient {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let mut child = Command::new(self.program)
+            .args(["--fail", "--silent", "--show-error", "--header", "@-", url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => format!(
+                    "Fetching needs `{}` on the PATH to make HTTPS requests, install it first",
+                    self.program
+                ),
+                _ => format!("Could not start `{}`: {}", self.program, e),
+            })?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        for (name, value) in headers {
+            writeln!(stdin, "{}: {}", name, value)?;
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "GET {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Plain `http://` over std, enough for local stand-in servers.
+pub struct PlainHttpClient;
+
+impl HttpClient for PlainHttpClient {
+    fn get(&self, url: &str, headers: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Not a plain http url: {}", url))?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+
+        let mut stream = TcpStream::connect(host)?;
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+            path, host
+        )?;
+        for (name, value) in headers {
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "\r\n")?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(format!("GET {} failed: {}", url, status_line.trim()).into());
+        }
+
+        // Skip the headers and read until the server closes the connection
+        let mut line = String::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line)?;
+            if line.trim_end().is_empty() {
+                break;
+            }
+        }
+        let mut body = String::new();
+        reader.read_to_string(&mut body)?;
+        Ok(body)
+    }
+}
+
+pub fn client_for(base_url: &str) -> Box<dyn HttpClient> {
+    if base_url.starts_with("http://") {
+        Box::new(PlainHttpClient)
+    } else {
+        Box::new(CurlClient::default())
+    }
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn strip_tags(s: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in s.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The first `<pre><code>` block following a mention of an example in the puzzle page.
+pub fn extract_example(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let mention = lower.find("example")?;
+    let start = lower[mention..].find("<pre><code>")? + mention + "<pre><code>".len();
+    let end = lower[start..].find("</code></pre>")? + start;
+
+    Some(unescape(&strip_tags(&html[start..end])))
+}
+
+pub struct Fetcher {
+    pub client: Box<dyn HttpClient>,
+    pub base_url: String,
+    pub year: u32,
+    pub session: String,
+    pub input_dir: PathBuf,
+    pub set: String,
+}
+
+impl Fetcher {
+    fn get(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        let url = format!("{}/{}{}", self.base_url, self.year, path);
+        let cookie = format!("session={}", self.session);
+        self.client
+            .get(&url, &[("Cookie", &cookie), ("User-Agent", USER_AGENT)])
+    }
+
+    fn cached(
+        &self,
+        path: PathBuf,
+        download: impl FnOnce() -> Result<String, Box<dyn Error>>,
+    ) -> Result<(PathBuf, bool), Box<dyn Error>> {
+        if path.exists() {
+            return Ok((path, false));
+        }
+        let content = download()?;
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, content)?;
+        Ok((path, true))
+    }
+
+    /// Returns the cache path and whether it was downloaded now.
+    pub fn fetch_input(&self, day: u8) -> Result<(PathBuf, bool), Box<dyn Error>> {
+        let path = input_path(&self.input_dir, &self.set, day);
+        self.cached(path, || self.get(&format!("/day/{}/input", day)))
+    }
+
+    /// The example block is stored as its own input set.
+    pub fn fetch_example(&self, day: u8) -> Result<(PathBuf, bool), Box<dyn Error>> {
+        let path = input_path(&self.input_dir, EXAMPLE_SET, day);
+        self.cached(path, || {
+            let html = self.get(&format!("/day/{}", day))?;
+            extract_example(&html).ok_or_else(|| format!("No example found for day {}", day).into())
+        })
+    }
+}
+
+pub fn read_session(path: &Path) -> Result<String, Box<dyn Error>> {
+    let session = fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Failed to read session token from {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    Ok(session.trim().to_string())
+}
+
+pub fn main<I: Iterator<Item = String>>(mut args: I) -> Result<(), Box<dyn Error>> {
+    let mut days: Vec<u8> = vec![];
+    let mut set = DEFAULT_SET.to_string();
+    let mut input_dir = PathBuf::from(INPUT_DIR);
+    let mut session_file = PathBuf::from(SESSION_FILE);
+    let mut base_url = BASE_URL.to_string();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--day" => {
+                let day = value("--day")?;
+                days.push(day.parse().map_err(|_| format!("Invalid day: {}", day))?);
+            }
+            "--set" => set = value("--set")?,
+            "--input-dir" => input_dir = PathBuf::from(value("--input-dir")?),
+            "--session-file" => session_file = PathBuf::from(value("--session-file")?),
+            "--base-url" => base_url = value("--base-url")?,
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+    if days.is_empty() {
+        return Err("fetch needs at least one --day".into());
+    }
+
+    let fetcher = Fetcher {
+        client: client_for(&base_url),
+        base_url,
+        year: crate::YEAR,
+        session: read_session(&session_file)?,
+        input_dir,
+        set,
+    };
+
+    for day in days {
+        for (what, fetched) in [
+            ("input", fetcher.fetch_input(day)),
+            ("example", fetcher.fetch_example(day)),
+        ] {
+            match fetched {
+                Ok((path, true)) => println!("Day {} {}: downloaded {}", day, what, path.display()),
+                Ok((path, false)) => println!("Day {} {}: cached {}", day, what, path.display()),
+                Err(e) => eprintln!("Day {} {}: {}", day, what, e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    static PUZZLE_PAGE: &str = "<article><p>The input is a list.</p>
+<p>For example:</p>
+<pre><code>3   4
+4   3
+<em>2</em>   5
+</code></pre>
+<p>Another block:</p><pre><code>nope</code></pre></article>";
+
+    type RequestLog = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Serves the puzzle page and input, recording the requested paths and cookies.
+    fn start_server() -> (String, RequestLog) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                let mut cookie = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Cookie: ") {
+                        cookie = value.trim().to_string();
+                    }
+                }
+                log.lock().unwrap().push((path.clone(), cookie));
+
+                let (status, body) = match path.as_str() {
+                    "/2024/day/1/input" => ("200 OK", "1   2\n"),
+                    "/2024/day/1" => ("200 OK", PUZZLE_PAGE),
+                    _ => ("404 Not Found", ""),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nConnection: close\r\n\r\n{}",
+                    status, body
+                )
+                .unwrap();
+            }
+        });
+
+        (format!("http://{}", addr), requests)
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc2024-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_extract_example() {
+        assert_eq!(
+            extract_example(PUZZLE_PAGE),
+            Some("3   4\n4   3\n2   5\n".to_string())
+        );
+        assert_eq!(
+            extract_example("<p>For example</p><pre><code>a &lt;b&gt; &amp;</code></pre>"),
+            Some("a <b> &".to_string())
+        );
+        assert_eq!(extract_example("<pre><code>no mention</code></pre>"), None);
+    }
+
+    #[test]
+    fn test_fetch_and_cache() {
+        let (base_url, requests) = start_server();
+        let dir = test_dir("fetch");
+        let fetcher = Fetcher {
+            client: client_for(&base_url),
+            base_url,
+            year: 2024,
+            session: "abc".to_string(),
+            input_dir: dir.clone(),
+            set: "alice".to_string(),
+        };
+
+        let (path, downloaded) = fetcher.fetch_input(1).unwrap();
+        assert!(downloaded);
+        assert_eq!(path, dir.join("alice/day1.txt"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1   2\n");
+
+        let (path, downloaded) = fetcher.fetch_example(1).unwrap();
+        assert!(downloaded);
+        assert_eq!(path, dir.join("example/day1.txt"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "3   4\n4   3\n2   5\n");
+
+        // Never re-downloaded
+        assert!(!fetcher.fetch_input(1).unwrap().1);
+        assert!(!fetcher.fetch_example(1).unwrap().1);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                ("/2024/day/1/input".to_string(), "session=abc".to_string()),
+                ("/2024/day/1".to_string(), "session=abc".to_string()),
+            ]
+        );
+
+        // Failures are not cached
+        assert!(fetcher.fetch_input(2).is_err());
+        assert!(!dir.join("alice/day2.txt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_curl_missing() {
+        let client = CurlClient {
+            program: "aoc2024-no-such-curl",
+        };
+        let e = client.get("https://example.com", &[]).unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("needs `aoc2024-no-such-curl` on the PATH"));
+    }
+
+    #[test]
+    fn test_read_session() {
+        let dir = test_dir("session");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".session"), "abc123\n").unwrap();
+        assert_eq!(read_session(&dir.join(".session")).unwrap(), "abc123");
+        assert!(read_session(&dir.join("missing")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
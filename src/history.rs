@@ -0,0 +1,358 @@
+use crate::runner::{Results, SOLUTIONS};
+use itertools::Itertools;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub timestamp: u64,
+    pub commit: String,
+    pub host: String,
+    pub set: String,
+    pub day: u8,
+    pub part: u8,
+    pub variant: String,
+    pub answer: Result<String, String>,
+    pub generator: Duration,
+    pub runner: Duration,
+}
+
+impl Record {
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "timestamp": self.timestamp,
+            "commit": self.commit,
+            "host": self.host,
+            "set": self.set,
+            "day": self.day,
+            "part": self.part,
+            "variant": self.variant,
+            "generator_ns": self.generator.as_nanos() as u64,
+            "runner_ns": self.runner.as_nanos() as u64,
+        });
+        match &self.answer {
+            Ok(answer) => value["answer"] = json!(answer),
+            Err(e) => value["error"] = json!(e),
+        }
+        value
+    }
+
+    pub fn from_json(value: &Value) -> Option<Record> {
+        let string = |key: &str| value[key].as_str().map(|s| s.to_string());
+        let number = |key: &str| value[key].as_u64();
+
+        Some(Record {
+            timestamp: number("timestamp")?,
+            commit: string("commit")?,
+            host: string("host")?,
+            set: string("set")?,
+            day: number("day")? as u8,
+            part: number("part")? as u8,
+            variant: string("variant")?,
+            answer: string("answer").ok_or_else(|| string("error").unwrap_or_default()),
+            generator: Duration::from_nanos(number("generator_ns")?),
+            runner: Duration::from_nanos(number("runner_ns")?),
+        })
+    }
+
+    pub fn total(&self) -> Duration {
+        self.generator + self.runner
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let out = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !out.trim().is_empty()).then(|| out.trim().to_string())
+}
+
+pub fn git_commit() -> String {
+    command_output("git", &["describe", "--always", "--dirty"]).unwrap_or("unknown".to_string())
+}
+
+pub fn host() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| command_output("hostname", &[]))
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or("unknown".to_string())
+}
+
+pub fn records(results: &Results, commit: &str, host: &str) -> Vec<Record> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    results
+        .outcomes
+        .iter()
+        .map(|((set, i), outcome)| Record {
+            timestamp,
+            commit: commit.to_string(),
+            host: host.to_string(),
+            set: set.clone(),
+            day: SOLUTIONS[*i].day,
+            part: SOLUTIONS[*i].part,
+            variant: SOLUTIONS[*i].variant_name().to_string(),
+            answer: outcome.answer.clone(),
+            generator: outcome.generator,
+            runner: outcome.runner,
+        })
+        .collect()
+}
+
+pub fn append(path: &Path, records: &[Record]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for record in records {
+        writeln!(file, "{}", record.to_json())?;
+    }
+    Ok(())
+}
+
+/// Unparseable lines are skipped so that a truncated write does not hide the rest.
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .filter_map(|value| Record::from_json(&value))
+        .collect())
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TrendPoint {
+    pub commit: String,
+    pub best: Duration,
+    pub runs: usize,
+    pub answer: Result<String, String>,
+    pub answer_changed: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Trend {
+    pub set: String,
+    pub day: u8,
+    pub part: u8,
+    pub variant: String,
+    pub points: Vec<TrendPoint>,
+}
+
+/// Best time per commit for every solution and set, in the order the commits were first seen.
+pub fn trends(records: &[Record]) -> Vec<Trend> {
+    records
+        .iter()
+        .into_group_map_by(|r| (r.set.clone(), r.day, r.part, r.variant.clone()))
+        .into_iter()
+        .sorted_by(|(a, _), (b, _)| (a.1, a.2, &a.3, &a.0).cmp(&(b.1, b.2, &b.3, &b.0)))
+        .map(|((set, day, part, variant), runs)| {
+            // Repeated runs on the same commit are merged, even with other commits in between
+            let mut by_commit: Vec<(&str, Vec<&Record>)> = vec![];
+            for run in runs {
+                match by_commit
+                    .iter_mut()
+                    .find(|(commit, _)| *commit == run.commit)
+                {
+                    Some((_, runs)) => runs.push(run),
+                    None => by_commit.push((&run.commit, vec![run])),
+                }
+            }
+
+            let mut points: Vec<TrendPoint> = vec![];
+            for (commit, runs) in by_commit {
+                let answer = runs.last().unwrap().answer.clone();
+                points.push(TrendPoint {
+                    commit: commit.to_string(),
+                    best: runs.iter().map(|r| r.total()).min().unwrap(),
+                    runs: runs.len(),
+                    answer_changed: points.last().is_some_and(|p| p.answer != answer),
+                    answer,
+                });
+            }
+            Trend {
+                set,
+                day,
+                part,
+                variant,
+                points,
+            }
+        })
+        .collect()
+}
+
+fn print_trends(trends: &[Trend]) {
+    for trend in trends {
+        println!(
+            "day{} part{} {} [{}]",
+            trend.day, trend.part, trend.variant, trend.set
+        );
+        let mut previous: Option<Duration> = None;
+        for point in &trend.points {
+            let change = previous
+                .map(|p| {
+                    let ratio = point.best.as_secs_f64() / p.as_secs_f64().max(f64::EPSILON);
+                    format!(" ({:+.0}%)", (ratio - 1.0) * 100.0)
+                })
+                .unwrap_or_default();
+            let answer = match &point.answer {
+                Ok(answer) => answer.clone(),
+                Err(e) => format!("FAILED {}", e),
+            };
+            println!(
+                "  {:<16} {:>12.2?}{:<8} x{:<3} {}{}",
+                point.commit,
+                point.best,
+                change,
+                point.runs,
+                answer,
+                if point.answer_changed {
+                    "  ! answer changed"
+                } else {
+                    ""
+                }
+            );
+            previous = Some(point.best);
+        }
+    }
+}
+
+pub fn main<I: Iterator<Item = String>>(mut args: I) -> Result<(), Box<dyn Error>> {
+    let mut path = PathBuf::from(HISTORY_FILE);
+    let mut days: Vec<u8> = vec![];
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--history" => path = PathBuf::from(value("--history")?),
+            "--day" => {
+                let day = value("--day")?;
+                days.push(day.parse().map_err(|_| format!("Invalid day: {}", day))?);
+            }
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    let records = load(&path)
+        .map_err(|e| format!("Failed to read history from {}: {}", path.display(), e))?
+        .into_iter()
+        .filter(|r| days.is_empty() || days.contains(&r.day))
+        .collect_vec();
+    print_trends(&trends(&records));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(commit: &str, variant: &str, answer: &str, ms: u64) -> Record {
+        Record {
+            timestamp: 1734825600,
+            commit: commit.to_string(),
+            host: "box".to_string(),
+            set: "2024".to_string(),
+            day: 22,
+            part: 2,
+            variant: variant.to_string(),
+            answer: Ok(answer.to_string()),
+            generator: Duration::from_millis(1),
+            runner: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let ok = record("abc", "faster", "1710", 5);
+        assert_eq!(Record::from_json(&ok.to_json()), Some(ok));
+
+        let failed = Record {
+            answer: Err("running: boom".to_string()),
+            ..record("abc", "faster", "", 5)
+        };
+        assert_eq!(Record::from_json(&failed.to_json()), Some(failed));
+
+        assert_eq!(Record::from_json(&json!({ "commit": "abc" })), None);
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("aoc2024-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let records = vec![
+            record("abc", "default", "1710", 9),
+            record("abc", "faster", "1710", 5),
+        ];
+        append(&path, &records[..1]).unwrap();
+        append(&path, &records[1..]).unwrap();
+        assert_eq!(load(&path).unwrap(), records);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_trends() {
+        let records = vec![
+            record("aaa", "faster", "1710", 9),
+            record("aaa", "default", "1710", 20),
+            record("aaa", "faster", "1710", 7),
+            record("bbb", "faster", "1710", 4),
+            record("ccc", "faster", "1711", 4),
+        ];
+
+        let trends = trends(&records);
+        assert_eq!(trends.len(), 2);
+        assert_eq!(trends[0].variant, "default");
+        assert_eq!(trends[0].points.len(), 1);
+
+        let faster = &trends[1];
+        assert_eq!(
+            faster
+                .points
+                .iter()
+                .map(|p| (p.commit.as_str(), p.best, p.runs, p.answer_changed))
+                .collect_vec(),
+            vec![
+                ("aaa", Duration::from_millis(8), 2, false),
+                ("bbb", Duration::from_millis(5), 1, false),
+                ("ccc", Duration::from_millis(5), 1, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trends_interleaved() {
+        // Going back to an older commit, e.g. when comparing branches
+        let records = vec![
+            record("aaa", "faster", "1710", 9),
+            record("bbb", "faster", "1711", 4),
+            record("aaa", "faster", "1710", 3),
+            record("ccc", "faster", "1711", 6),
+            record("bbb", "faster", "1711", 8),
+        ];
+
+        let trends = trends(&records);
+        assert_eq!(
+            trends[0]
+                .points
+                .iter()
+                .map(|p| (p.commit.as_str(), p.best, p.runs, p.answer_changed))
+                .collect_vec(),
+            vec![
+                ("aaa", Duration::from_millis(4), 2, false),
+                ("bbb", Duration::from_millis(5), 2, true),
+                ("ccc", Duration::from_millis(7), 1, false),
+            ]
+        );
+    }
+}
@@ -27,4 +27,10 @@ mod day_7;
 mod day_8;
 mod day_9;
 
+pub mod fetch;
+pub mod history;
+pub mod report;
+pub mod runner;
+pub mod serve;
+
 aoc_lib! { year = 2024 }
@@ -1,3 +1,24 @@
-use aoc_runner_derive::aoc_main;
+use aoc2024::runner::CountingAllocator;
+use std::process::ExitCode;
 
-aoc_main! { lib = aoc2024 }
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() -> ExitCode {
+    CountingAllocator::enable();
+    let mut args = std::env::args().skip(1).peekable();
+    let result = match args.peek().map(String::as_str) {
+        Some("fetch") => aoc2024::fetch::main(args.skip(1)),
+        Some("history") => aoc2024::history::main(args.skip(1)),
+        Some("serve") => aoc2024::serve::main(args.skip(1)),
+        _ => aoc2024::runner::main(args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
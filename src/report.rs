@@ -0,0 +1,325 @@
+use crate::runner::{panic_message, Results, SOLUTIONS};
+use itertools::Itertools;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::Path;
+
+const CELL_SIZE: usize = 4;
+
+// None when the input can't be rendered
+type RenderFn = fn(&str) -> Option<Render>;
+
+/// A grid of colour classes for embedding into the report. Class 0 is the background.
+pub struct Render {
+    pub title: String,
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u32>,
+}
+
+impl Render {
+    pub fn new(title: &str, width: usize, height: usize) -> Render {
+        Render {
+            title: title.to_string(),
+            width,
+            height,
+            cells: vec![0; width * height],
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, class: u32) {
+        self.cells[y * self.width + x] = class;
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg width=\"{}\" height=\"{}\" shape-rendering=\"crispEdges\">",
+            self.width * CELL_SIZE,
+            self.height * CELL_SIZE
+        );
+        for (i, class) in self.cells.iter().enumerate() {
+            if *class == 0 {
+                continue;
+            }
+            let _ = write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{CELL_SIZE}\" height=\"{CELL_SIZE}\" fill=\"{}\"/>",
+                (i % self.width) * CELL_SIZE,
+                (i / self.width) * CELL_SIZE,
+                colour(*class)
+            );
+        }
+        svg += "</svg>";
+        svg
+    }
+}
+
+// Golden angle hue steps keep neighbouring classes apart
+fn colour(class: u32) -> String {
+    format!("hsl({}, 65%, 50%)", (class as u64 * 137) % 360)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn answer_table(html: &mut String, results: &Results) {
+    let rows = results.outcomes.keys().map(|(_, i)| *i).unique().sorted();
+    let disagreements = results.disagreements();
+
+    html.push_str("<h2>Answers</h2>\n<table>\n<tr><th>Solution</th>");
+    for set in &results.sets {
+        let _ = write!(html, "<th>{}</th>", escape(set));
+    }
+    html.push_str("</tr>\n");
+
+    for i in rows {
+        let solution = &SOLUTIONS[i];
+        let _ = write!(html, "<tr><td>{}</td>", escape(&solution.name()));
+        for set in &results.sets {
+            let flagged = disagreements
+                .iter()
+                .any(|d| &d.set == set && d.day == solution.day && d.part == solution.part);
+            let cell = match results.get(set, i).map(|o| &o.answer) {
+                Some(Ok(answer)) => escape(answer),
+                Some(Err(e)) => format!("FAILED {}", escape(e)),
+                None => "-".to_string(),
+            };
+            let class = if flagged { " class=\"disagree\"" } else { "" };
+            let _ = write!(html, "<td{}>{}</td>", class, cell);
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+}
+
+fn timing_charts(html: &mut String, results: &Results) {
+    html.push_str("<h2>Timings</h2>\n");
+
+    let parts = results
+        .outcomes
+        .keys()
+        .map(|(_, i)| (SOLUTIONS[*i].day, SOLUTIONS[*i].part))
+        .unique()
+        .sorted();
+
+    for (day, part) in parts {
+        let bars = results
+            .outcomes
+            .iter()
+            .filter(|((_, i), _)| SOLUTIONS[*i].day == day && SOLUTIONS[*i].part == part)
+            .map(|((set, i), outcome)| (set, &SOLUTIONS[*i], outcome))
+            .collect_vec();
+        let max = bars
+            .iter()
+            .map(|(_, _, o)| o.total().as_secs_f64())
+            .fold(f64::EPSILON, f64::max);
+
+        let _ = writeln!(
+            html,
+            "<h3>Day {} - Part {}</h3>\n<table class=\"bars\">",
+            day, part
+        );
+        for (set, solution, outcome) in bars {
+            let _ = writeln!(
+                html,
+                "<tr><td>{} {}</td><td><div class=\"bar\" style=\"width: {:.1}%\"></div></td><td>{:.2?}</td></tr>",
+                escape(set),
+                escape(solution.variant_name()),
+                100.0 * outcome.total().as_secs_f64() / max,
+                outcome.total()
+            );
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+fn allocation_table(html: &mut String, results: &Results) {
+    if results.outcomes.values().all(|o| o.allocations.is_none()) {
+        return;
+    }
+
+    html.push_str(
+        "<h2>Allocations</h2>\n<table>\n<tr><th>Set</th><th>Solution</th><th>Allocations</th><th>Bytes</th></tr>\n",
+    );
+    for ((set, i), outcome) in &results.outcomes {
+        if let Some(allocations) = &outcome.allocations {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(set),
+                escape(&SOLUTIONS[*i].name()),
+                allocations.count,
+                allocations.bytes
+            );
+        }
+    }
+    html.push_str("</table>\n");
+}
+
+pub fn render_html(results: &Results, renders: &[(String, Render)]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Advent of Code 2024</title>
+<style>
+body { font-family: sans-serif; }
+td, th { padding: 2px 8px; text-align: left; }
+td.disagree { background: #f99; }
+table.bars td:nth-child(2) { width: 400px; }
+div.bar { background: #48c; height: 12px; }
+</style>
+</head>
+<body>
+<h1>Advent of Code 2024</h1>
+",
+    );
+
+    answer_table(&mut html, results);
+    timing_charts(&mut html, results);
+    allocation_table(&mut html, results);
+
+    if !renders.is_empty() {
+        html.push_str("<h2>Renders</h2>\n");
+    }
+    for (set, render) in renders {
+        let _ = writeln!(
+            html,
+            "<h3>{} - {}</h3>\n{}",
+            escape(set),
+            escape(&render.title),
+            render.to_svg()
+        );
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Renders of the grid days for every set that has an input for them.
+pub fn renders(results: &Results, input_dir: &Path) -> Vec<(String, Render)> {
+    let days: [(u8, RenderFn); 4] = [
+        (6, crate::day_6::render),
+        (8, crate::day_8::render),
+        (12, crate::day_12::render),
+        (14, crate::day_14::render),
+    ];
+
+    let mut renders = vec![];
+    for set in &results.sets {
+        for (day, render) in days {
+            let ran = results
+                .outcomes
+                .keys()
+                .any(|(s, i)| s == set && SOLUTIONS[*i].day == day);
+            if !ran {
+                continue;
+            }
+            let path = crate::runner::input_path(input_dir, set, day);
+            if let Ok(input) = fs::read_to_string(path) {
+                // A malformed grid loses its render, not the finished batch
+                match panic::catch_unwind(|| render(input.trim_end_matches('\n'))) {
+                    Ok(Some(render)) => renders.push((set.clone(), render)),
+                    Ok(None) => {}
+                    Err(payload) => eprintln!(
+                        "Day {} render failed for set {}: {}",
+                        day,
+                        set,
+                        panic_message(&*payload)
+                    ),
+                }
+            }
+        }
+    }
+    renders
+}
+
+pub fn write_report(path: &Path, results: &Results, input_dir: &Path) -> io::Result<()> {
+    fs::write(path, render_html(results, &renders(results, input_dir)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::Outcome;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_svg() {
+        let mut render = Render::new("test", 3, 2);
+        render.set(2, 1, 1);
+        assert_eq!(render.get(2, 1), 1);
+        assert_eq!(
+            render.to_svg(),
+            "<svg width=\"12\" height=\"8\" shape-rendering=\"crispEdges\">\
+<rect x=\"8\" y=\"4\" width=\"4\" height=\"4\" fill=\"hsl(137, 65%, 50%)\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let results = Results {
+            sets: vec!["a<b".to_string()],
+            outcomes: BTreeMap::from([(
+                ("a<b".to_string(), 0),
+                Outcome {
+                    answer: Ok("11".to_string()),
+                    generator: Duration::from_millis(1),
+                    runner: Duration::from_millis(2),
+                    allocations: None,
+                },
+            )]),
+        };
+        let html = render_html(&results, &[("a<b".to_string(), Render::new("grid", 1, 1))]);
+
+        assert!(html.contains("<th>a&lt;b</th>"));
+        assert!(html.contains("<td>day1 part1</td><td>11</td>"));
+        assert!(html.contains("width: 100.0%"));
+        assert!(!html.contains("<h2>Allocations</h2>"));
+        assert!(html.contains("<h3>a&lt;b - grid</h3>"));
+        // Self-contained
+        assert!(!html.contains("src="));
+        assert!(!html.contains("href="));
+    }
+
+    #[test]
+    fn test_renders_survive_panics() {
+        let dir = std::env::temp_dir().join(format!("aoc2024-renders-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        // No robots: the day 14 render has no frame to pick
+        fs::write(dir.join("a/day14.txt"), "").unwrap();
+        fs::write(dir.join("a/day12.txt"), "AB\nAA").unwrap();
+
+        let outcome = Outcome {
+            answer: Ok("0".to_string()),
+            generator: Duration::ZERO,
+            runner: Duration::ZERO,
+            allocations: None,
+        };
+        let index = |day| SOLUTIONS.iter().position(|s| s.day == day).unwrap();
+        let results = Results {
+            sets: vec!["a".to_string()],
+            outcomes: BTreeMap::from([
+                (("a".to_string(), index(12)), outcome.clone()),
+                (("a".to_string(), index(14)), outcome),
+            ]),
+        };
+
+        let renders = renders(&results, &dir);
+        assert_eq!(renders.len(), 1);
+        assert_eq!(renders[0].1.title, "Day 12 garden regions");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,803 @@
+use aoc_runner::{ArcStr, Runner};
+use itertools::Itertools;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub const INPUT_DIR: &str = "input";
+pub const DEFAULT_SET: &str = "2024";
+
+type Factory = fn(ArcStr) -> Result<Box<dyn Runner>, Box<dyn Error>>;
+
+pub struct Solution {
+    pub day: u8,
+    pub part: u8,
+    pub variant: Option<&'static str>,
+    factory: Factory,
+}
+
+impl Solution {
+    pub fn name(&self) -> String {
+        match self.variant {
+            Some(variant) => format!("day{} part{} {}", self.day, self.part, variant),
+            None => format!("day{} part{}", self.day, self.part),
+        }
+    }
+
+    pub fn variant_name(&self) -> &'static str {
+        self.variant.unwrap_or("default")
+    }
+
+    pub fn run(&self, input: &str) -> Outcome {
+        let before = CountingAllocator::snapshot();
+        let start_time = Instant::now();
+        let runner = match (self.factory)(ArcStr::from(input)) {
+            Ok(runner) => runner,
+            Err(e) => {
+                return Outcome {
+                    answer: Err(format!("generating: {}", e)),
+                    generator: start_time.elapsed(),
+                    runner: Duration::ZERO,
+                    allocations: None,
+                }
+            }
+        };
+
+        let inter_time = Instant::now();
+        let answer = runner
+            .try_run()
+            .map(|result| result.to_string())
+            .map_err(|e| format!("running: {}", e));
+        let final_time = Instant::now();
+        let after = CountingAllocator::snapshot();
+
+        Outcome {
+            answer,
+            generator: inter_time - start_time,
+            runner: final_time - inter_time,
+            allocations: before.zip(after).map(|(before, after)| Allocations {
+                count: after.count - before.count,
+                bytes: after.bytes - before.bytes,
+            }),
+        }
+    }
+}
+
+macro_rules! solution {
+    ($day:literal, $part:literal, $t:ident :: $f:ident) => {
+        Solution {
+            day: $day,
+            part: $part,
+            variant: None,
+            factory: <crate::Factory as crate::$t>::$f,
+        }
+    };
+    ($day:literal, $part:literal, $variant:literal, $t:ident :: $f:ident) => {
+        Solution {
+            day: $day,
+            part: $part,
+            variant: Some($variant),
+            factory: <crate::Factory as crate::$t>::$f,
+        }
+    };
+}
+
+// Keep in sync with the `#[aoc]` attributes of the enabled days in `lib.rs`, checked by
+// `test_solutions_match_aoc_attributes`.
+pub static SOLUTIONS: &[Solution] = &[
+    solution!(1, 1, Day1Part1::day1_part1),
+    solution!(1, 1, "external", Day1Part1EXTERNAL::day1_part1_external),
+    solution!(1, 2, Day1Part2::day1_part2),
+    solution!(1, 2, "external", Day1Part2EXTERNAL::day1_part2_external),
+    solution!(2, 1, Day2Part1::day2_part1),
+    solution!(2, 2, Day2Part2::day2_part2),
+    solution!(2, 2, "dampener", Day2Part2DAMPENER::day2_part2_dampener),
+    solution!(3, 1, Day3Part1::day3_part1),
+    solution!(3, 1, "streaming", Day3Part1STREAMING::day3_part1_streaming),
+    solution!(3, 2, Day3Part2::day3_part2),
+    solution!(3, 2, "streaming", Day3Part2STREAMING::day3_part2_streaming),
+    solution!(4, 1, Day4Part1::day4_part1),
+    solution!(4, 2, Day4Part2::day4_part2),
+    solution!(5, 1, Day5Part1::day5_part1),
+    solution!(5, 2, Day5Part2::day5_part2),
+    solution!(6, 1, Day6Part1::day6_part1),
+    solution!(6, 2, "faster", Day6Part2FASTER::day6_part2_faster),
+    solution!(6, 2, "jump", Day6Part2JUMP::day6_part2_jump),
+    solution!(6, 2, "parallel", Day6Part2PARALLEL::day6_part2_parallel),
+    solution!(7, 1, Day7Part1::day7_part1),
+    solution!(7, 1, "forward", Day7Part1FORWARD::day7_part1_forward),
+    solution!(7, 1, "wide", Day7Part1WIDE::day7_part1_wide),
+    solution!(7, 2, Day7Part2::day7_part2),
+    solution!(7, 2, "forward", Day7Part2FORWARD::day7_part2_forward),
+    solution!(7, 2, "faster", Day7Part2FASTER::day7_part2_faster),
+    solution!(7, 2, "wide", Day7Part2WIDE::day7_part2_wide),
+    solution!(8, 1, Day8Part1::day8_part1),
+    solution!(8, 2, Day8Part2::day8_part2),
+    solution!(9, 1, Day9Part1::day9_part1),
+    solution!(9, 2, Day9Part2::day9_part2),
+    solution!(10, 1, Day10Part1::day10_part1),
+    solution!(10, 2, Day10Part2::day10_part2),
+    solution!(11, 1, Day11Part1::day11_part1),
+    solution!(11, 2, Day11Part2::day11_part2),
+    solution!(12, 1, Day12Part1::day12_part1),
+    solution!(12, 2, Day12Part2::day12_part2),
+    solution!(13, 1, Day13Part1::day13_part1),
+    solution!(13, 1, "brute", Day13Part1BRUTE::day13_part1_brute),
+    solution!(13, 2, Day13Part2::day13_part2),
+    solution!(14, 1, Day14Part1::day14_part1),
+    solution!(14, 2, Day14Part2::day14_part2),
+    solution!(15, 1, Day15Part1::day15_part1),
+    solution!(15, 2, Day15Part2::day15_part2),
+    solution!(16, 1, Day16Part1::day16_part1),
+    solution!(16, 2, Day16Part2::day16_part2),
+    solution!(17, 1, Day17Part1::day17_part1),
+    solution!(17, 2, Day17Part2::day17_part2),
+    solution!(18, 1, Day18Part1::day18_part1),
+    solution!(
+        18,
+        2,
+        "binary_search",
+        Day18Part2BINARY_SEARCH::day18_part2_binary_search
+    ),
+    solution!(19, 1, Day19Part1::day19_part1),
+    solution!(19, 2, Day19Part2::day19_part2),
+    solution!(20, 1, Day20Part1::day20_part1),
+    solution!(20, 2, Day20Part2::day20_part2),
+    solution!(21, 1, Day21Part1::day21_part1),
+    solution!(21, 2, Day21Part2::day21_part2),
+    solution!(22, 1, Day22Part1::day22_part1),
+    solution!(22, 2, Day22Part2::day22_part2),
+    solution!(22, 2, "faster", Day22Part2FASTER::day22_part2_faster),
+    solution!(23, 1, Day23Part1::day23_part1),
+    solution!(23, 2, Day23Part2::day23_part2),
+];
+
+type DiagnosticsFn = fn(&str) -> String;
+
+// Extra per-day output about the input, shown with `--diagnostics`
+static DIAGNOSTICS: &[(u8, DiagnosticsFn)] = &[
+    (1, crate::day_1::diagnostics),
+    (2, crate::day_2::diagnostics),
+    (3, crate::day_3::diagnostics),
+    (4, crate::day_4::diagnostics),
+    (5, crate::day_5::diagnostics),
+    (6, crate::day_6::diagnostics),
+    (7, crate::day_7::diagnostics),
+    (8, crate::day_8::diagnostics),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Allocations {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // (count, bytes). Per thread, so that concurrent runs do not see each other's allocations
+    // and parallel variants do not contend on shared counters.
+    static THREAD_ALLOCATIONS: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+/// Counts allocations when installed as the `#[global_allocator]` of the binary and enabled.
+/// Only allocations of the calling thread are counted, not those of the worker threads of
+/// parallel variants.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    /// Turns counting on, once, next to installing the allocator.
+    pub fn enable() {
+        COUNTING.store(true, Ordering::Relaxed);
+    }
+
+    /// Totals of the current thread so far, or `None` when counting is off.
+    pub fn snapshot() -> Option<Allocations> {
+        if !COUNTING.load(Ordering::Relaxed) {
+            return None;
+        }
+        THREAD_ALLOCATIONS
+            .try_with(|counts| {
+                let (count, bytes) = counts.get();
+                Allocations { count, bytes }
+            })
+            .ok()
+    }
+
+    fn count(bytes: usize) {
+        if COUNTING.load(Ordering::Relaxed) {
+            // Fails only while the thread is being torn down
+            let _ = THREAD_ALLOCATIONS.try_with(|counts| {
+                let (count, total) = counts.get();
+                counts.set((count + 1, total + bytes));
+            });
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::count(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub answer: Result<String, String>,
+    pub generator: Duration,
+    pub runner: Duration,
+    pub allocations: Option<Allocations>,
+}
+
+impl Outcome {
+    pub fn total(&self) -> Duration {
+        self.generator + self.runner
+    }
+}
+
+pub fn input_path(input_dir: &Path, set: &str, day: u8) -> PathBuf {
+    input_dir.join(set).join(format!("day{}.txt", day))
+}
+
+/// Every sub-directory of the input dir is an input set.
+pub fn list_sets(input_dir: &Path) -> io::Result<Vec<String>> {
+    let mut sets = vec![];
+    for entry in fs::read_dir(input_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            sets.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    sets.sort();
+    Ok(sets)
+}
+
+pub struct Options {
+    pub input_dir: PathBuf,
+    pub sets: Vec<String>,
+    pub batch: bool,
+    pub days: Vec<u8>,
+    pub report: Option<PathBuf>,
+    pub history: Option<PathBuf>,
+    pub diagnostics: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            input_dir: PathBuf::from(INPUT_DIR),
+            sets: vec![],
+            batch: false,
+            days: vec![],
+            report: None,
+            history: Some(PathBuf::from(crate::history::HISTORY_FILE)),
+            diagnostics: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+            match arg.as_str() {
+                "--set" => {
+                    // A repeated set would overwrite its own results
+                    let set = value("--set")?;
+                    if !options.sets.contains(&set) {
+                        options.sets.push(set);
+                    }
+                }
+                "--input-dir" => options.input_dir = PathBuf::from(value("--input-dir")?),
+                "--day" => {
+                    let day = value("--day")?;
+                    options
+                        .days
+                        .push(day.parse().map_err(|_| format!("Invalid day: {}", day))?);
+                }
+                "--batch" => options.batch = true,
+                "--report" => options.report = Some(PathBuf::from(value("--report")?)),
+                "--history" => options.history = Some(PathBuf::from(value("--history")?)),
+                "--no-history" => options.history = None,
+                "--diagnostics" => options.diagnostics = true,
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    pub fn resolve_sets(&self) -> io::Result<Vec<String>> {
+        if self.batch {
+            list_sets(&self.input_dir)
+        } else if self.sets.is_empty() {
+            Ok(vec![DEFAULT_SET.to_string()])
+        } else {
+            Ok(self.sets.clone())
+        }
+    }
+}
+
+/// Outcomes keyed by (set, index into `SOLUTIONS`). Days without an input file are skipped.
+pub struct Results {
+    pub sets: Vec<String>,
+    pub outcomes: BTreeMap<(String, usize), Outcome>,
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// A panic on a malformed input fails its cell instead of the whole batch.
+fn run_guarded(solution: &Solution, input: &str) -> Outcome {
+    panic::catch_unwind(AssertUnwindSafe(|| solution.run(input))).unwrap_or_else(|payload| {
+        Outcome {
+            answer: Err(format!("panicked: {}", panic_message(&*payload))),
+            generator: Duration::ZERO,
+            runner: Duration::ZERO,
+            allocations: None,
+        }
+    })
+}
+
+pub fn run_sets(options: &Options, sets: &[String]) -> Results {
+    let mut outcomes = BTreeMap::new();
+
+    for set in sets {
+        let mut inputs: BTreeMap<u8, Option<String>> = BTreeMap::new();
+        for (i, solution) in SOLUTIONS.iter().enumerate() {
+            if !options.days.is_empty() && !options.days.contains(&solution.day) {
+                continue;
+            }
+            let input = inputs.entry(solution.day).or_insert_with(|| {
+                fs::read_to_string(input_path(&options.input_dir, set, solution.day)).ok()
+            });
+            if let Some(input) = input {
+                outcomes.insert((set.clone(), i), run_guarded(solution, input));
+            }
+        }
+    }
+
+    Results {
+        sets: sets.to_vec(),
+        outcomes,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Disagreement {
+    pub set: String,
+    pub day: u8,
+    pub part: u8,
+    pub answers: Vec<(&'static str, String)>,
+}
+
+impl Results {
+    pub fn get(&self, set: &str, solution: usize) -> Option<&Outcome> {
+        self.outcomes.get(&(set.to_string(), solution))
+    }
+
+    /// Variants of the same day and part that produced different answers for a set.
+    pub fn disagreements(&self) -> Vec<Disagreement> {
+        let mut by_part: BTreeMap<_, Vec<(&'static str, &str)>> = BTreeMap::new();
+        for ((set, i), outcome) in &self.outcomes {
+            let solution = &SOLUTIONS[*i];
+            if let Ok(answer) = &outcome.answer {
+                by_part
+                    .entry((set.as_str(), solution.day, solution.part))
+                    .or_default()
+                    .push((solution.variant_name(), answer));
+            }
+        }
+
+        by_part
+            .into_iter()
+            .filter(|(_, answers)| {
+                answers
+                    .iter()
+                    .map(|(_, a)| a)
+                    .collect::<BTreeSet<_>>()
+                    .len()
+                    > 1
+            })
+            .map(|((set, day, part), answers)| Disagreement {
+                set: set.to_string(),
+                day,
+                part,
+                answers: answers
+                    .into_iter()
+                    .map(|(variant, answer)| (variant, answer.to_string()))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn print_matrix(&self) {
+        let disagreements = self.disagreements();
+        let rows = self
+            .outcomes
+            .keys()
+            .map(|(_, i)| *i)
+            .unique()
+            .sorted()
+            .collect_vec();
+
+        let cell = |set: &str, i: usize| -> String {
+            match self.get(set, i) {
+                Some(Outcome {
+                    answer: Ok(answer), ..
+                }) => {
+                    let flag = disagreements.iter().any(|d| {
+                        d.set == set && d.day == SOLUTIONS[i].day && d.part == SOLUTIONS[i].part
+                    });
+                    format!(
+                        "{}{} ({:.2?})",
+                        answer,
+                        if flag { " !" } else { "" },
+                        self.get(set, i).unwrap().total()
+                    )
+                }
+                Some(Outcome { answer: Err(e), .. }) => format!("FAILED {}", e),
+                None => "-".to_string(),
+            }
+        };
+
+        let names = rows.iter().map(|i| SOLUTIONS[*i].name()).collect_vec();
+        let cells = rows
+            .iter()
+            .map(|i| self.sets.iter().map(|set| cell(set, *i)).collect_vec())
+            .collect_vec();
+
+        let name_width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+        let widths = (0..self.sets.len())
+            .map(|c| {
+                cells
+                    .iter()
+                    .map(|row| row[c].chars().count())
+                    .chain([self.sets[c].len()])
+                    .max()
+                    .unwrap()
+            })
+            .collect_vec();
+
+        let line = |first: &str, row: &[String]| {
+            let mut line = format!("{:name_width$}", first);
+            for (cell, width) in row.iter().zip(&widths) {
+                line += &format!("  {:width$}", cell);
+            }
+            println!("{}", line.trim_end());
+        };
+
+        line("", &self.sets);
+        for (name, row) in names.iter().zip(&cells) {
+            line(name, row);
+        }
+
+        for d in disagreements {
+            println!(
+                "! {} day{} part{}: {}",
+                d.set,
+                d.day,
+                d.part,
+                d.answers
+                    .iter()
+                    .map(|(variant, answer)| format!("{}={}", variant, answer))
+                    .join(", ")
+            );
+        }
+    }
+
+    /// The classic aoc-runner output for a single set.
+    pub fn print_single(&self) {
+        for ((_, i), outcome) in &self.outcomes {
+            let solution = &SOLUTIONS[*i];
+            let title = match solution.variant {
+                Some(variant) => format!(
+                    "Day {} - Part {} - {}",
+                    solution.day, solution.part, variant
+                ),
+                None => format!("Day {} - Part {}", solution.day, solution.part),
+            };
+            match &outcome.answer {
+                Ok(answer) => println!(
+                    "{}: {}\n\tgenerator: {:?},\n\trunner: {:?}\n",
+                    title, answer, outcome.generator, outcome.runner
+                ),
+                Err(e) => eprintln!("{}: FAILED while {}\n", title, e),
+            }
+        }
+        for d in self.disagreements() {
+            println!(
+                "! Day {} - Part {}: variants disagree: {}",
+                d.day,
+                d.part,
+                d.answers
+                    .iter()
+                    .map(|(variant, answer)| format!("{}={}", variant, answer))
+                    .join(", ")
+            );
+        }
+    }
+}
+
+pub fn print_diagnostics(options: &Options, sets: &[String]) {
+    for set in sets {
+        for (day, diagnostics) in DIAGNOSTICS {
+            if !options.days.is_empty() && !options.days.contains(day) {
+                continue;
+            }
+            if let Ok(input) = fs::read_to_string(input_path(&options.input_dir, set, *day)) {
+                println!("Day {} diagnostics [{}]:", day, set);
+                for line in diagnostics(input.trim_end_matches('\n')).lines() {
+                    println!("\t{}", line);
+                }
+                println!();
+            }
+        }
+    }
+}
+
+pub fn main<I: Iterator<Item = String>>(args: I) -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(args)?;
+    let sets = options.resolve_sets()?;
+
+    println!("Advent of code {}", crate::YEAR);
+
+    let results = run_sets(&options, &sets);
+    if options.batch || sets.len() > 1 {
+        results.print_matrix();
+    } else {
+        results.print_single();
+    }
+
+    if options.diagnostics {
+        print_diagnostics(&options, &sets);
+    }
+
+    if let Some(path) = &options.history {
+        let records = crate::history::records(
+            &results,
+            &crate::history::git_commit(),
+            &crate::history::host(),
+        );
+        crate::history::append(path, &records)?;
+    }
+
+    if let Some(path) = &options.report {
+        crate::report::write_report(path, &results, &options.input_dir)?;
+        println!("Report written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aoc2024-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn outcome(answer: &str) -> Outcome {
+        Outcome {
+            answer: Ok(answer.to_string()),
+            generator: Duration::ZERO,
+            runner: Duration::ZERO,
+            allocations: None,
+        }
+    }
+
+    fn index_of(day: u8, part: u8, variant: Option<&str>) -> usize {
+        SOLUTIONS
+            .iter()
+            .position(|s| s.day == day && s.part == part && s.variant == variant)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args = [
+            "--set",
+            "alice",
+            "--set",
+            "bob",
+            "--set",
+            "alice",
+            "--day",
+            "7",
+            "--input-dir",
+            "x",
+            "--report",
+            "r.html",
+            "--no-history",
+            "--diagnostics",
+        ];
+        let options = Options::parse(args.iter().map(|s| s.to_string())).unwrap();
+        assert_eq!(options.sets, vec!["alice", "bob"]);
+        assert_eq!(options.days, vec![7]);
+        assert_eq!(options.input_dir, PathBuf::from("x"));
+        assert_eq!(options.report, Some(PathBuf::from("r.html")));
+        assert_eq!(options.history, None);
+        assert!(options.diagnostics);
+        assert!(!options.batch);
+
+        assert!(Options::parse(["--day".to_string()].into_iter()).is_err());
+        assert!(Options::parse(["--nope".to_string()].into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_batch_runs_all_sets() {
+        let dir = test_dir("batch");
+        fs::create_dir_all(dir.join("alice")).unwrap();
+        fs::create_dir_all(dir.join("bob")).unwrap();
+        fs::write(
+            dir.join("alice/day1.txt"),
+            "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n",
+        )
+        .unwrap();
+        fs::write(dir.join("bob/day1.txt"), "1   2\n").unwrap();
+
+        let options = Options {
+            input_dir: dir.clone(),
+            batch: true,
+            days: vec![1, 2],
+            ..Options::default()
+        };
+        let sets = options.resolve_sets().unwrap();
+        assert_eq!(sets, vec!["alice", "bob"]);
+
+        let results = run_sets(&options, &sets);
+        let part1 = index_of(1, 1, None);
+        let part2 = index_of(1, 2, None);
+        assert_eq!(
+            results.get("alice", part1).unwrap().answer,
+            Ok("11".to_string())
+        );
+        assert_eq!(
+            results.get("alice", part2).unwrap().answer,
+            Ok("31".to_string())
+        );
+        assert_eq!(
+            results.get("bob", part1).unwrap().answer,
+            Ok("1".to_string())
+        );
+        // no day 2 inputs
+        assert!(results.get("alice", index_of(2, 1, None)).is_none());
+        assert!(results.disagreements().is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_disagreements() {
+        let default = index_of(7, 2, None);
+        let faster = index_of(7, 2, Some("faster"));
+        let results = Results {
+            sets: vec!["a".to_string(), "b".to_string()],
+            outcomes: BTreeMap::from([
+                (("a".to_string(), default), outcome("11387")),
+                (("a".to_string(), faster), outcome("11387")),
+                (("b".to_string(), default), outcome("11387")),
+                (("b".to_string(), faster), outcome("11388")),
+            ]),
+        };
+
+        assert_eq!(
+            results.disagreements(),
+            vec![Disagreement {
+                set: "b".to_string(),
+                day: 7,
+                part: 2,
+                answers: vec![
+                    ("default", "11387".to_string()),
+                    ("faster", "11388".to_string())
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_allocations_per_thread() {
+        // The test binary keeps the system allocator, so only these calls are counted
+        CountingAllocator::enable();
+        let before = CountingAllocator::snapshot().unwrap();
+        CountingAllocator::count(100);
+        std::thread::spawn(|| CountingAllocator::count(1000))
+            .join()
+            .unwrap();
+        let after = CountingAllocator::snapshot().unwrap();
+        assert_eq!(
+            (after.count - before.count, after.bytes - before.bytes),
+            (1, 100)
+        );
+    }
+
+    #[test]
+    fn test_panic_fails_one_cell() {
+        let dir = test_dir("panic");
+        fs::create_dir_all(dir.join("good")).unwrap();
+        fs::create_dir_all(dir.join("bad")).unwrap();
+        fs::write(dir.join("good/day4.txt"), "XMAS\n").unwrap();
+        // The day 4 generator indexes the first row
+        fs::write(dir.join("bad/day4.txt"), "").unwrap();
+
+        let options = Options {
+            input_dir: dir.clone(),
+            batch: true,
+            days: vec![4],
+            ..Options::default()
+        };
+        let results = run_sets(&options, &options.resolve_sets().unwrap());
+        let part1 = index_of(4, 1, None);
+        assert_eq!(
+            results.get("good", part1).unwrap().answer,
+            Ok("1".to_string())
+        );
+        let failed = &results.get("bad", part1).unwrap().answer;
+        assert!(failed.as_ref().is_err_and(|e| e.starts_with("panicked")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_solutions_match_aoc_attributes() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let lib = fs::read_to_string(src.join("lib.rs")).unwrap();
+        let attribute =
+            regex::Regex::new(r"(?m)^#\[aoc\(day(\d+), ?part(\d)(?:, ?(\w+))?\)\]").unwrap();
+
+        let mut attributes = BTreeSet::new();
+        for module in lib
+            .lines()
+            .filter_map(|line| line.strip_prefix("mod "))
+            .filter_map(|line| line.split(';').next())
+        {
+            let code = fs::read_to_string(src.join(format!("{}.rs", module))).unwrap();
+            for cap in attribute.captures_iter(&code) {
+                attributes.insert((
+                    cap[1].parse::<u8>().unwrap(),
+                    cap[2].parse::<u8>().unwrap(),
+                    cap.get(3).map(|v| v.as_str().to_string()),
+                ));
+            }
+        }
+
+        let registered = SOLUTIONS
+            .iter()
+            .map(|s| (s.day, s.part, s.variant.map(|v| v.to_string())))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(registered.len(), SOLUTIONS.len(), "duplicate entries");
+        assert_eq!(registered, attributes);
+    }
+}
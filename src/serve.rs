@@ -0,0 +1,415 @@
+use crate::runner::{Solution, SOLUTIONS};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic;
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8024";
+// Puzzle inputs are a few dozen KiB
+pub const MAX_BODY: usize = 1 << 20;
+pub const MAX_HEADER_LINE: usize = 8 << 10;
+pub const MAX_HEADERS: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    Io(io::Error),
+    Invalid(&'static str),
+    HeadersTooLarge,
+    BodyTooLarge,
+}
+
+impl RequestError {
+    fn status(&self) -> u16 {
+        match self {
+            RequestError::Io(_) | RequestError::Invalid(_) => 400,
+            RequestError::HeadersTooLarge => 431,
+            RequestError::BodyTooLarge => 413,
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Io(e) => write!(f, "{}", e),
+            RequestError::Invalid(msg) => write!(f, "{}", msg),
+            RequestError::HeadersTooLarge => write!(
+                f,
+                "more than {} headers or a line longer than {} bytes",
+                MAX_HEADERS, MAX_HEADER_LINE
+            ),
+            RequestError::BodyTooLarge => write!(f, "body larger than {} bytes", MAX_BODY),
+        }
+    }
+}
+
+impl Error for RequestError {}
+
+impl From<io::Error> for RequestError {
+    fn from(e: io::Error) -> Self {
+        RequestError::Io(e)
+    }
+}
+
+// Reads at most `MAX_HEADER_LINE` bytes, so that an endless line can't exhaust memory
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, RequestError> {
+    let mut line = String::new();
+    reader
+        .take(MAX_HEADER_LINE as u64 + 1)
+        .read_line(&mut line)?;
+    if line.len() > MAX_HEADER_LINE {
+        return Err(RequestError::HeadersTooLarge);
+    }
+    Ok(line)
+}
+
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, RequestError> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(RequestError::Invalid("malformed request line"));
+    };
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+
+    let mut content_length = 0;
+    for headers in 0.. {
+        let header = read_line(reader)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(RequestError::HeadersTooLarge);
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| RequestError::Invalid("invalid content-length"))?;
+            }
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Err(RequestError::BodyTooLarge);
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        body: String::from_utf8(body).map_err(|_| RequestError::Invalid("body is not utf-8"))?,
+    })
+}
+
+fn solution_json(solution: &Solution) -> Value {
+    json!({
+        "day": solution.day,
+        "part": solution.part,
+        "variant": solution.variant_name(),
+    })
+}
+
+fn days() -> Response {
+    Response {
+        status: 200,
+        body: Value::Array(SOLUTIONS.iter().map(solution_json).collect()),
+    }
+}
+
+fn solve(day: &str, part: &str, query: Option<&str>, input: &str) -> Response {
+    let (Ok(day), Ok(part)) = (day.parse::<u8>(), part.parse::<u8>()) else {
+        return Response::error(400, "day and part must be numbers");
+    };
+    let variant = query.and_then(|q| {
+        q.split('&')
+            .find_map(|pair| pair.strip_prefix("variant="))
+            .map(|v| v.to_string())
+    });
+
+    // Without an explicit variant, the first registered one for the part is used
+    let Some(solution) = SOLUTIONS.iter().find(|s| {
+        s.day == day && s.part == part && variant.as_ref().is_none_or(|v| v == s.variant_name())
+    }) else {
+        return Response::error(404, "no such solution");
+    };
+
+    let outcome = match panic::catch_unwind(|| solution.run(input)) {
+        Ok(outcome) => outcome,
+        Err(_) => return Response::error(500, "solution panicked on the input"),
+    };
+
+    let total = outcome.total();
+    match outcome.answer {
+        Ok(answer) => {
+            let mut body = solution_json(solution);
+            body["answer"] = json!(answer);
+            body["timing"] = json!({
+                "generator_ns": outcome.generator.as_nanos() as u64,
+                "runner_ns": outcome.runner.as_nanos() as u64,
+                "total_ns": total.as_nanos() as u64,
+            });
+            // Counted on this connection's thread, so concurrent requests don't mix
+            if let Some(allocations) = outcome.allocations {
+                body["allocations"] = json!({
+                    "count": allocations.count,
+                    "bytes": allocations.bytes,
+                });
+            }
+            Response { status: 200, body }
+        }
+        Err(e) => Response::error(400, &e),
+    }
+}
+
+pub fn handle(request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+    match (request.method.as_str(), &segments[..]) {
+        ("GET", ["days"]) => days(),
+        ("POST", ["solve", day, part]) => solve(day, part, request.query.as_deref(), &request.body),
+        (_, ["days"]) | (_, ["solve", _, _]) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let response = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => handle(&request),
+        Err(e) => Response::error(e.status(), &e.to_string()),
+    };
+
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.reason(),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream) {
+                eprintln!("Connection failed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+pub fn main<I: Iterator<Item = String>>(mut args: I) -> Result<(), Box<dyn Error>> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => addr = args.next().ok_or("--addr needs a value")?,
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    let listener = TcpListener::bind(&addr)?;
+    println!("Serving on http://{}", listener.local_addr()?);
+    serve(listener)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener));
+        addr
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /solve/1/2?variant=faster HTTP/1.1\r\nContent-Length: 3\r\n\r\n1 2";
+        assert_eq!(
+            read_request(&mut raw.as_bytes()).unwrap(),
+            Request {
+                method: "POST".to_string(),
+                path: "/solve/1/2".to_string(),
+                query: Some("variant=faster".to_string()),
+                body: "1 2".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_body_too_large() {
+        let raw = format!(
+            "POST /solve/1/1 HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            usize::MAX
+        );
+        let e = read_request(&mut raw.as_bytes()).unwrap_err();
+        assert!(matches!(e, RequestError::BodyTooLarge));
+
+        let addr = start_server();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{}", raw).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large"));
+    }
+
+    #[test]
+    fn test_headers_too_large() {
+        let long = format!(
+            "GET /days HTTP/1.1\r\nX: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_LINE)
+        );
+        let e = read_request(&mut long.as_bytes()).unwrap_err();
+        assert!(matches!(e, RequestError::HeadersTooLarge));
+        assert_eq!(e.status(), 431);
+
+        let many = format!(
+            "GET /days HTTP/1.1\r\n{}\r\n",
+            "X: y\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert!(matches!(
+            read_request(&mut many.as_bytes()),
+            Err(RequestError::HeadersTooLarge)
+        ));
+        let enough = format!(
+            "GET /days HTTP/1.1\r\n{}\r\n",
+            "X: y\r\n".repeat(MAX_HEADERS)
+        );
+        assert!(read_request(&mut enough.as_bytes()).is_ok());
+
+        // An endless line is cut off without waiting for its end
+        let mut endless = "GET /days HTTP/1.1\r\nX: "
+            .as_bytes()
+            .chain(io::repeat(b'a'));
+        let mut reader = BufReader::new(&mut endless);
+        assert!(matches!(
+            read_request(&mut reader),
+            Err(RequestError::HeadersTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_days() {
+        let addr = start_server();
+        let (status, body) = request(addr, "GET", "/days", "");
+        assert_eq!(status, 200);
+        assert!(body
+            .as_array()
+            .unwrap()
+            .contains(&json!({ "day": 7, "part": 2, "variant": "faster" })));
+    }
+
+    #[test]
+    fn test_solve() {
+        let addr = start_server();
+        let input = "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+
+        let (status, body) = request(addr, "POST", "/solve/1/1", input);
+        assert_eq!(status, 200);
+        assert_eq!(body["answer"], "11");
+        assert_eq!(body["variant"], "default");
+        assert!(body["timing"]["total_ns"].is_u64());
+
+        let (status, body) = request(addr, "POST", "/solve/1/2", input);
+        assert_eq!(status, 200);
+        assert_eq!(body["answer"], "31");
+    }
+
+    #[test]
+    fn test_solve_variant() {
+        let addr = start_server();
+        let input = "190: 10 19\n3267: 81 40 27\n156: 15 6\n";
+
+        let (status, body) = request(addr, "POST", "/solve/7/2?variant=faster", input);
+        assert_eq!(status, 200);
+        assert_eq!(body["variant"], "faster");
+        assert_eq!(body["answer"], "3613");
+    }
+
+    #[test]
+    fn test_errors() {
+        let addr = start_server();
+        assert_eq!(request(addr, "POST", "/solve/26/1", "").0, 404);
+        assert_eq!(request(addr, "POST", "/solve/x/1", "").0, 400);
+        assert_eq!(request(addr, "GET", "/solve/1/1", "").0, 405);
+        assert_eq!(request(addr, "GET", "/nope", "").0, 404);
+        assert_eq!(request(addr, "POST", "/solve/1/1", "garbage").0, 400);
+        // The day 4 generator indexes the first row
+        assert_eq!(request(addr, "POST", "/solve/4/1", "").0, 500);
+    }
+}